@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Error returned by every Tauri command.
+///
+/// Serialized as `{ "kind": "...", "details": ... }` so the frontend can tell
+/// a missing binary apart from a network or parse failure.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "details")]
+pub enum AppError {
+    /// yt-dlp wasn't found in `bin/` or the app resources.
+    BinaryNotFound,
+    /// ffmpeg is needed for the requested operation but couldn't be run.
+    FfmpegNotFound(String),
+    /// The requested external downloader couldn't be run.
+    DownloaderNotFound(String),
+    /// A subprocess couldn't be started.
+    SpawnFailed(String),
+    /// yt-dlp ran but exited unsuccessfully.
+    YtDlpFailed { code: Option<i32>, stderr: String },
+    /// yt-dlp couldn't reach the site through the configured proxy.
+    ProxyFailed { proxy: String, stderr: String },
+    /// No extractor supports the URL.
+    UnsupportedUrl(String),
+    /// The video was removed or isn't available in this region.
+    VideoUnavailable(String),
+    /// The video is private.
+    PrivateVideo(String),
+    /// The site wants a signed-in user, e.g. for age-restricted videos.
+    SignInRequired(String),
+    /// The requested impersonation target isn't supported by this yt-dlp
+    /// build, usually because curl-cffi is missing.
+    ImpersonationUnavailable(String),
+    /// The URL has no downloadable formats, e.g. because it's a playlist.
+    NoFormats(String),
+    /// yt-dlp's JSON output couldn't be parsed.
+    JsonParse(String),
+    /// A command argument was rejected before running yt-dlp.
+    InvalidInput(String),
+    /// The downloaded file is missing, empty or not a valid media file.
+    VerifyFailed(String),
+    /// The operation was cancelled by the user.
+    Cancelled,
+    /// The download was paused and can be resumed with `resume_download`.
+    Paused,
+    /// `download_all_formats` was called without `confirm`. Says how much it
+    /// would download, with the size of the formats that report one.
+    ConfirmationRequired {
+        format_count: usize,
+        total_bytes: Option<u64>,
+    },
+    /// The media didn't fit in the in-memory download limit.
+    BufferLimitExceeded { limit: u64 },
+    /// yt-dlp didn't finish in time and was killed.
+    Timeout { seconds: u64 },
+    /// Waiting on, killing or talking to a subprocess failed.
+    Io(String),
+    /// An unexpected internal failure, e.g. a regex that doesn't compile.
+    Internal(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BinaryNotFound => {
+                write!(f, "yt-dlp not found in bin directory or app resources")
+            }
+            AppError::FfmpegNotFound(reason) => write!(f, "{}", reason),
+            AppError::DownloaderNotFound(reason) => write!(f, "{}", reason),
+            AppError::SpawnFailed(e) => write!(f, "Failed to spawn process: {}", e),
+            AppError::YtDlpFailed { code, stderr } => match code {
+                Some(code) => write!(f, "yt-dlp failed with exit code {}: {}", code, stderr),
+                None => write!(f, "yt-dlp was terminated: {}", stderr),
+            },
+            AppError::ProxyFailed { proxy, stderr } => {
+                write!(f, "Could not connect through proxy {}: {}", proxy, stderr)
+            }
+            AppError::UnsupportedUrl(_) => write!(f, "This site or URL isn't supported"),
+            AppError::VideoUnavailable(_) => write!(f, "This video is unavailable"),
+            AppError::PrivateVideo(_) => write!(f, "This video is private"),
+            AppError::SignInRequired(_) => {
+                write!(
+                    f,
+                    "This video requires signing in, try loading browser cookies"
+                )
+            }
+            AppError::ImpersonationUnavailable(_) => write!(
+                f,
+                "Browser impersonation isn't available in this yt-dlp build (curl-cffi is missing)"
+            ),
+            AppError::NoFormats(e) => write!(f, "{}", e),
+            AppError::JsonParse(e) => write!(f, "Failed to parse JSON: {}", e),
+            AppError::InvalidInput(e) => write!(f, "{}", e),
+            AppError::VerifyFailed(e) => write!(f, "Download verification failed: {}", e),
+            AppError::Cancelled => write!(f, "Cancelled"),
+            AppError::Paused => write!(f, "Paused"),
+            AppError::ConfirmationRequired {
+                format_count,
+                total_bytes,
+            } => match total_bytes {
+                Some(bytes) => write!(
+                    f,
+                    "This would download {} formats (at least {} bytes), confirm to continue",
+                    format_count, bytes
+                ),
+                None => write!(
+                    f,
+                    "This would download {} formats, confirm to continue",
+                    format_count
+                ),
+            },
+            AppError::BufferLimitExceeded { limit } => {
+                write!(
+                    f,
+                    "The media is larger than the {} byte in-memory limit",
+                    limit
+                )
+            }
+            AppError::Timeout { seconds } => {
+                write!(f, "yt-dlp didn't finish within {} seconds", seconds)
+            }
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Internal(e) => write!(f, "Internal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl AppError {
+    /// Maps well-known yt-dlp `ERROR:` messages to a specific variant carrying
+    /// the offending line, or returns `None` if nothing in `stderr` matches.
+    pub fn from_ytdlp_stderr(stderr: &str) -> Option<Self> {
+        stderr
+            .lines()
+            .filter(|line| line.starts_with("ERROR:"))
+            .find_map(|line| {
+                let variant: fn(String) -> AppError = if line.contains("Unsupported URL") {
+                    AppError::UnsupportedUrl
+                } else if line.contains("Private video") {
+                    AppError::PrivateVideo
+                } else if line.contains("Sign in to confirm") {
+                    AppError::SignInRequired
+                } else if line.contains("Video unavailable") {
+                    AppError::VideoUnavailable
+                } else if line.contains("Impersonate target") && line.contains("not available") {
+                    AppError::ImpersonationUnavailable
+                } else {
+                    return None;
+                };
+                Some(variant(line.to_string()))
+            })
+    }
+}
+
+impl From<regex::Error> for AppError {
+    fn from(e: regex::Error) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
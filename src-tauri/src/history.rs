@@ -0,0 +1,110 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Mutex;
+
+const HISTORY_FILE: &str = "history.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: Option<String>,
+    pub format: String,
+    /// Downloaded file if yt-dlp reported one, otherwise the target directory.
+    pub path: Option<String>,
+    /// Seconds since the Unix epoch when the download finished.
+    pub timestamp: u64,
+    pub success: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        url: String,
+        title: Option<String>,
+        format: String,
+        path: Option<String>,
+        success: bool,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        HistoryEntry {
+            url,
+            title,
+            format,
+            path,
+            timestamp,
+            success,
+        }
+    }
+}
+
+/// Serializes access to the history file between concurrent downloads.
+#[derive(Default)]
+pub struct HistoryStore {
+    lock: Mutex<()>,
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Io(format!("Failed to resolve app data directory: {}", e)))?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+fn read_entries(path: &Path) -> Result<Vec<HistoryEntry>, AppError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Io(format!("Failed to read download history: {}", e)))?;
+    serde_json::from_str(&contents).map_err(|e| AppError::JsonParse(e.to_string()))
+}
+
+fn write_entries(path: &Path, entries: &[HistoryEntry]) -> Result<(), AppError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::Io(format!("Failed to create app data directory: {}", e)))?;
+    }
+
+    let contents =
+        serde_json::to_string_pretty(entries).map_err(|e| AppError::JsonParse(e.to_string()))?;
+    std::fs::write(path, contents)
+        .map_err(|e| AppError::Io(format!("Failed to write download history: {}", e)))
+}
+
+/// Appends an entry to the history file.
+pub async fn record(
+    app: &AppHandle,
+    history: &HistoryStore,
+    entry: HistoryEntry,
+) -> Result<(), AppError> {
+    let _guard = history.lock.lock().await;
+    let path = history_path(app)?;
+    let mut entries = read_entries(&path)?;
+    entries.push(entry);
+    write_entries(&path, &entries)
+}
+
+#[tauri::command]
+pub async fn get_download_history(
+    app: AppHandle,
+    history: State<'_, HistoryStore>,
+) -> Result<Vec<HistoryEntry>, AppError> {
+    let _guard = history.lock.lock().await;
+    read_entries(&history_path(&app)?)
+}
+
+#[tauri::command]
+pub async fn clear_download_history(
+    app: AppHandle,
+    history: State<'_, HistoryStore>,
+) -> Result<(), AppError> {
+    let _guard = history.lock.lock().await;
+    write_entries(&history_path(&app)?, &[])
+}
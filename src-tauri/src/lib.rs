@@ -1,10 +1,23 @@
+mod error;
+mod history;
+mod settings;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use error::AppError;
+use history::{HistoryEntry, HistoryStore};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use settings::{Settings, SettingsStore};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tauri::{Emitter, Window};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, RunEvent, State, Window};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, Notify, Semaphore};
 
 #[derive(Debug, Serialize, Deserialize)]
 struct VideoFormat {
@@ -23,42 +36,922 @@ struct QualityOption {
     id: String,
     label: String,
     format_type: String, // "video+audio", "video", "audio"
+    filesize_bytes: Option<u64>,
+    fps: Option<u32>,
+    dynamic_range: Option<String>, // "SDR", "HDR10", "HLG", ...
+    abr: Option<f64>,              // audio bitrate in kbps
+    /// Whether a video+audio download of this option merges separate streams
+    /// and so needs ffmpeg, unlike pre-muxed formats.
+    requires_merge: bool,
 }
 
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+/// Exact size of a yt-dlp format entry, falling back to its approximate size.
+fn format_filesize(format: &serde_json::Value) -> Option<u64> {
+    format["filesize"]
+        .as_u64()
+        .or_else(|| format["filesize_approx"].as_u64())
+}
+
+#[derive(Debug, Serialize)]
+struct VideoInfo {
+    title: Option<String>,
+    duration_seconds: Option<f64>,
+    thumbnail_url: Option<String>,
+    uploader: Option<String>,
+    view_count: Option<u64>,
+    qualities: Vec<QualityOption>,
+}
+
+/// Options that affect how yt-dlp reaches the site, shared by the info and
+/// download commands.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchOptions {
+    /// Browser to load cookies from, optionally with a profile (e.g. `firefox:work`).
+    cookies_from_browser: Option<String>,
+    /// Netscape-format cookies file.
+    cookies_file: Option<String>,
+    /// Proxy URL using one of `PROXY_SCHEMES`, e.g. `socks5://127.0.0.1:1080`.
+    proxy: Option<String>,
+    /// Fake an `X-Forwarded-For` header to get around geographic restrictions.
+    #[serde(default)]
+    geo_bypass: bool,
+    /// Two-letter ISO 3166 country code to use for the geo bypass.
+    geo_bypass_country: Option<String>,
+    /// Browser to impersonate, e.g. `chrome` or `safari:macos`. Needs a yt-dlp
+    /// build with curl-cffi.
+    impersonate: Option<String>,
+    /// Custom `User-Agent` header.
+    user_agent: Option<String>,
+    /// `Referer` header, needed by sites that only serve embedded players.
+    referer: Option<String>,
+    /// Extra HTTP headers as `Name: value`, e.g. `["Origin: https://example.com"]`.
+    headers: Option<Vec<String>>,
+    /// YouTube player client to extract with, one of `YOUTUBE_CLIENTS`.
+    /// Switching clients often gets around throttling and bot checks.
+    youtube_client: Option<String>,
+}
+
+/// Containers yt-dlp can embed a thumbnail into.
+const THUMBNAIL_CONTAINERS: &[&str] = &[
+    "mp3", "mkv", "mka", "ogg", "opus", "flac", "m4a", "mp4", "m4v", "mov",
+];
+
+/// Whether an `ERROR:` line from yt-dlp is a post-processing failure about the
+/// thumbnail, e.g. "ERROR: Postprocessing: Supported filetypes for thumbnail
+/// embedding are: ...". Only meaningful once `[EmbedThumbnail]` has run.
+fn is_thumbnail_embed_error(line: &str) -> bool {
+    line.starts_with("ERROR: Postprocessing:") && line.to_lowercase().contains("thumbnail")
+}
+
+/// What the stderr reader task saw by the time yt-dlp exited.
+#[derive(Default)]
+struct StderrSummary {
+    proxy_failed: bool,
+    /// `ERROR:` lines that weren't about thumbnail embedding.
+    errors: Vec<String>,
+    thumbnail_embed_failed: bool,
+    /// The last `STDERR_TAIL_LINES` lines, returned with failed downloads.
+    tail: VecDeque<String>,
+}
+
+const STDERR_TAIL_LINES: usize = 20;
+
+impl StderrSummary {
+    fn push_tail(&mut self, line: &str) {
+        if self.tail.len() == STDERR_TAIL_LINES {
+            self.tail.pop_front();
+        }
+        self.tail.push_back(line.to_string());
+    }
+
+    /// Exit status followed by the captured stderr tail.
+    fn failure_details(&self, status: std::process::ExitStatus) -> String {
+        let mut details = format!("Download failed with status: {}", status);
+        for line in &self.tail {
+            details.push('\n');
+            details.push_str(line);
+        }
+        details
+    }
+}
+
+/// Player clients accepted by `FetchOptions::youtube_client`.
+const YOUTUBE_CLIENTS: &[&str] = &[
+    "web",
+    "web_safari",
+    "web_embedded",
+    "web_music",
+    "web_creator",
+    "mweb",
+    "android",
+    "android_vr",
+    "ios",
+    "tv",
+    "tv_embedded",
+];
+
+/// Checks that a cookies file exists and is in the Netscape format yt-dlp
+/// reads, so a badly exported file fails loudly instead of being ignored.
+fn check_cookies_file(path: &Path) -> Result<(), AppError> {
+    if !path.is_file() {
+        return Err(AppError::InvalidInput(format!(
+            "Cookies file not found: {}",
+            path.display()
+        )));
+    }
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Io(format!("Failed to read cookies file: {}", e)))?;
+
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+    let Some(first) = lines.next() else {
+        return Err(AppError::InvalidInput(format!(
+            "Cookies file is empty: {}",
+            path.display()
+        )));
+    };
+    let has_header =
+        first.starts_with("# Netscape HTTP Cookie File") || first.starts_with("# HTTP Cookie File");
+    // Data lines have 7 tab-separated fields; "#HttpOnly_" marks a cookie, not a comment
+    let is_cookie_line = |line: &&str| {
+        (!line.starts_with('#') || line.starts_with("#HttpOnly_")) && line.split('\t').count() == 7
+    };
+    if has_header
+        || std::iter::once(first)
+            .chain(lines)
+            .any(|line| is_cookie_line(&line))
+    {
+        return Ok(());
+    }
+
+    Err(AppError::InvalidInput(format!(
+        "{} isn't a Netscape-format cookies file. Export it with a \"cookies.txt\" browser extension: it should start with \"# Netscape HTTP Cookie File\" and have one tab-separated cookie per line",
+        path.display()
+    )))
 }
 
+/// Validates a cookies file before it's used as `FetchOptions::cookies_file`.
 #[tauri::command]
-async fn get_video_info(url: String) -> Result<Vec<QualityOption>, String> {
-    println!("Fetching video info for: {}", url);
+fn validate_cookies_file(path: String) -> Result<(), AppError> {
+    check_cookies_file(Path::new(&path))
+}
+
+/// Proxy schemes accepted by `FetchOptions::proxy`.
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5"];
+
+/// Whether yt-dlp's stderr output points at a proxy connection failure.
+fn is_proxy_error(stderr: &str) -> bool {
+    const PATTERNS: &[&str] = &[
+        "Unable to connect to proxy",
+        "ProxyError",
+        "Tunnel connection failed",
+        "SOCKS",
+    ];
+    PATTERNS.iter().any(|pattern| stderr.contains(pattern))
+}
 
-    let bin_path = Path::new("bin/yt-dlp.exe");
-    if !bin_path.exists() {
-        return Err("yt-dlp.exe not found in bin directory".to_string());
+/// Browsers supported by yt-dlp's `--cookies-from-browser`.
+const COOKIE_BROWSERS: &[&str] = &[
+    "brave", "chrome", "chromium", "edge", "firefox", "opera", "safari", "vivaldi", "whale",
+];
+
+impl FetchOptions {
+    /// Validates the options and converts them into yt-dlp arguments.
+    fn to_args(&self) -> Result<Vec<String>, AppError> {
+        let mut args = Vec::new();
+
+        if let Some(browser) = &self.cookies_from_browser {
+            // The browser name may be followed by "+keyring" or ":profile"
+            let name = browser.split([':', '+']).next().unwrap_or_default();
+            if !COOKIE_BROWSERS.contains(&name.to_lowercase().as_str()) {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported browser for cookies: {} (expected one of {})",
+                    browser,
+                    COOKIE_BROWSERS.join(", ")
+                )));
+            }
+            args.push("--cookies-from-browser".to_string());
+            args.push(browser.clone());
+        }
+
+        if let Some(cookies_file) = &self.cookies_file {
+            check_cookies_file(Path::new(cookies_file))?;
+            args.push("--cookies".to_string());
+            args.push(cookies_file.clone());
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let scheme = proxy.split_once("://").map(|(scheme, _)| scheme);
+            if !scheme.is_some_and(|scheme| PROXY_SCHEMES.contains(&scheme.to_lowercase().as_str()))
+            {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported proxy URL: {} (expected a {} URL)",
+                    proxy,
+                    PROXY_SCHEMES.join("/")
+                )));
+            }
+            args.push("--proxy".to_string());
+            args.push(proxy.clone());
+        }
+
+        if self.geo_bypass {
+            args.push("--geo-bypass".to_string());
+        }
+
+        if let Some(country) = &self.geo_bypass_country {
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid country code: {} (expected a two-letter ISO code such as US)",
+                    country
+                )));
+            }
+            args.push("--geo-bypass-country".to_string());
+            args.push(country.to_uppercase());
+        }
+
+        if let Some(target) = &self.impersonate {
+            let valid = !target.is_empty()
+                && target
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+            if !valid {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid impersonate target: {} (expected e.g. chrome or safari:macos)",
+                    target
+                )));
+            }
+            args.push("--impersonate".to_string());
+            args.push(target.clone());
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            if user_agent.trim().is_empty() || user_agent.contains(['\n', '\r']) {
+                return Err(AppError::InvalidInput(
+                    "User agent must be a single non-empty line".to_string(),
+                ));
+            }
+            args.push("--user-agent".to_string());
+            args.push(user_agent.clone());
+        }
+
+        if let Some(referer) = &self.referer {
+            if referer.trim().is_empty() || referer.contains(['\n', '\r']) {
+                return Err(AppError::InvalidInput(
+                    "Referer must be a single non-empty line".to_string(),
+                ));
+            }
+            args.push("--referer".to_string());
+            args.push(referer.clone());
+        }
+
+        for header in self.headers.iter().flatten() {
+            let valid = header
+                .split_once(':')
+                .is_some_and(|(name, _)| !name.trim().is_empty());
+            if !valid || header.contains(['\n', '\r']) {
+                return Err(AppError::InvalidInput(format!(
+                    "Invalid header: {} (expected Name: value)",
+                    header
+                )));
+            }
+            args.push("--add-header".to_string());
+            args.push(header.clone());
+        }
+
+        if let Some(client) = &self.youtube_client {
+            if !YOUTUBE_CLIENTS.contains(&client.as_str()) {
+                return Err(AppError::InvalidInput(format!(
+                    "Unsupported YouTube client: {} (expected one of {})",
+                    client,
+                    YOUTUBE_CLIENTS.join(", ")
+                )));
+            }
+            args.push("--extractor-args".to_string());
+            args.push(format!("youtube:player_client={}", client));
+        }
+
+        Ok(args)
     }
+}
 
-    // Use --dump-json to get video metadata
-    // Use --dump-json to get video metadata
-    let output = Command::new(bin_path)
-        .args(&["--dump-json", "--no-playlist", url.as_str()])
-        .stdout(Stdio::piped())
+#[cfg(windows)]
+const YTDLP_BINARY: &str = "yt-dlp.exe";
+#[cfg(not(windows))]
+const YTDLP_BINARY: &str = "yt-dlp";
+
+/// Locates the yt-dlp binary: the `ytdlp_path` setting if it's usable, then
+/// the local `bin/` directory and the app's bundled resources.
+async fn resolve_ytdlp_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    match settings::load(app, &app.state::<SettingsStore>()).await {
+        Ok(Settings {
+            ytdlp_path: Some(path),
+            ..
+        }) => {
+            let path = PathBuf::from(path);
+            if settings::is_executable(&path) {
+                return Ok(path);
+            }
+            println!("Ignoring ytdlp_path setting, not an executable: {:?}", path);
+        }
+        Ok(_) => {}
+        Err(e) => println!("Failed to load settings: {}", e),
+    }
+
+    let mut candidates = vec![PathBuf::from("bin").join(YTDLP_BINARY)];
+    if let Ok(resource_dir) = app.path().resource_dir() {
+        candidates.push(resource_dir.join("bin").join(YTDLP_BINARY));
+        candidates.push(resource_dir.join(YTDLP_BINARY));
+    }
+
+    candidates
+        .iter()
+        .find(|path| path.exists())
+        .cloned()
+        .ok_or_else(|| {
+            println!(
+                "{} not found in bin directory or app resources. Current dir: {:?}, Checked paths: {:?}",
+                YTDLP_BINARY,
+                std::env::current_dir(),
+                candidates
+            );
+            AppError::BinaryNotFound
+        })
+}
+
+/// Keeps long titles within Windows' 260 character path limit, leaving room
+/// for the download directory. Other platforms allow 255 byte names.
+#[cfg(windows)]
+const DEFAULT_MAX_FILENAME_LENGTH: Option<u32> = Some(150);
+#[cfg(not(windows))]
+const DEFAULT_MAX_FILENAME_LENGTH: Option<u32> = None;
+
+#[cfg(windows)]
+const FFMPEG_BINARY: &str = "ffmpeg.exe";
+#[cfg(not(windows))]
+const FFMPEG_BINARY: &str = "ffmpeg";
+#[cfg(windows)]
+const FFPROBE_BINARY: &str = "ffprobe.exe";
+#[cfg(not(windows))]
+const FFPROBE_BINARY: &str = "ffprobe";
+
+#[cfg(windows)]
+const ARIA2C_BINARY: &str = "aria2c.exe";
+#[cfg(not(windows))]
+const ARIA2C_BINARY: &str = "aria2c";
+
+/// Finds a working `binary`, trying the local `bin/` directory before PATH.
+/// Returns the path to invoke along with the first line printed for `version_arg`.
+async fn find_binary(binary: &str, version_arg: &str) -> Option<(PathBuf, String)> {
+    let candidates = [PathBuf::from("bin").join(binary), PathBuf::from(binary)];
+
+    for candidate in candidates {
+        let Ok(output) = Command::new(&candidate)
+            .arg(version_arg)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .await
+        else {
+            continue;
+        };
+
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next().unwrap_or_default().trim().to_string();
+            return Some((candidate, version));
+        }
+    }
+    None
+}
+
+/// Finds a working ffmpeg, trying the local `bin/` directory before PATH.
+/// Returns the path to invoke along with the first line of `ffmpeg -version`.
+async fn find_ffmpeg() -> Result<(PathBuf, String), AppError> {
+    find_binary(FFMPEG_BINARY, "-version").await.ok_or_else(|| {
+        AppError::FfmpegNotFound("ffmpeg not found in bin directory or PATH".to_string())
+    })
+}
+
+/// Combines an audio file with the `.jpg` cover art next to it into an MP4
+/// with the image as a still video track, then removes the cover art.
+async fn render_audio_as_video(audio: &Path) -> Result<PathBuf, AppError> {
+    let (ffmpeg_path, _) = find_ffmpeg().await?;
+    let cover = audio.with_extension("jpg");
+    if !cover.is_file() {
+        return Err(AppError::Io(format!(
+            "Cover art not found: {}",
+            cover.display()
+        )));
+    }
+
+    let video = audio.with_extension("mp4");
+    let output = Command::new(&ffmpeg_path)
+        .args(["-y", "-loop", "1", "-i"])
+        .arg(&cover)
+        .arg("-i")
+        .arg(audio)
+        .args([
+            "-c:v",
+            "libx264",
+            "-tune",
+            "stillimage",
+            "-pix_fmt",
+            "yuv420p",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "192k",
+            "-shortest",
+        ])
+        .arg(&video)
+        .stdout(Stdio::null())
         .stderr(Stdio::piped())
         .output()
         .await
-        .map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to fetch video info: {}", stderr));
+        return Err(AppError::Io(format!(
+            "ffmpeg failed to render video: {}",
+            stderr.lines().last().unwrap_or_default()
+        )));
     }
 
-    // Parse JSON response
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value =
-        serde_json::from_str(&json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+    let _ = std::fs::remove_file(&cover);
+    Ok(video)
+}
+
+/// Re-encodes a downloaded video with ffmpeg, replacing it with a file in the
+/// codec's container. Done here rather than with `--recode-video`, which
+/// skips files that are already in the target container.
+async fn recode_video_file(
+    video: &Path,
+    codec: &(&str, &str, &str, &str),
+    crf: Option<u32>,
+) -> Result<PathBuf, AppError> {
+    let (ffmpeg_path, _) = find_ffmpeg().await?;
+    let (_, container, video_encoder, audio_encoder) = codec;
+    let target = video.with_extension(container);
+    // ffmpeg can't write over its input, so go through a temp file
+    let temp = video.with_extension(format!("recode.{}", container));
+
+    let mut command = Command::new(&ffmpeg_path);
+    command
+        .args(["-y", "-i"])
+        .arg(video)
+        // Capital V leaves out attached pictures such as cover art
+        .args(["-map", "0:V", "-map", "0:a?", "-c:v", video_encoder])
+        .args(["-c:a", audio_encoder]);
+    if let Some(crf) = crf {
+        command.args(["-crf", &crf.to_string()]);
+        // libvpx-vp9 only honours -crf in constant quality mode
+        if *video_encoder == "libvpx-vp9" {
+            command.args(["-b:v", "0"]);
+        }
+    }
+    // Lets QuickTime and Safari play HEVC in MP4
+    if *video_encoder == "libx265" {
+        command.args(["-tag:v", "hvc1"]);
+    }
+    let output = command
+        .arg(&temp)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&temp);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::Io(format!(
+            "ffmpeg failed to re-encode video: {}",
+            stderr.lines().last().unwrap_or_default()
+        )));
+    }
+
+    std::fs::rename(&temp, &target)
+        .map_err(|e| AppError::Io(format!("Failed to replace re-encoded video: {}", e)))?;
+    if target != video {
+        let _ = std::fs::remove_file(video);
+    }
+    Ok(target)
+}
+
+/// Checks that a downloaded file exists, isn't empty and, when ffprobe is
+/// available next to ffmpeg, that it opens as a media container.
+async fn verify_download(path: &Path) -> Result<(), AppError> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| AppError::VerifyFailed(format!("{}: {}", path.display(), e)))?
+        .len();
+    if size == 0 {
+        return Err(AppError::VerifyFailed(format!(
+            "{} is empty",
+            path.display()
+        )));
+    }
+
+    let Ok((ffmpeg_path, _)) = find_ffmpeg().await else {
+        println!("ffmpeg not found, skipping ffprobe check");
+        return Ok(());
+    };
+    let ffprobe_path = ffmpeg_path.with_file_name(FFPROBE_BINARY);
+    let output = match Command::new(&ffprobe_path)
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=format_name",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            println!("Could not run {:?}, skipping check: {}", ffprobe_path, e);
+            return Ok(());
+        }
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::VerifyFailed(format!(
+            "{} isn't a valid media file: {}",
+            path.display(),
+            stderr.lines().last().unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn check_ffmpeg() -> Result<String, AppError> {
+    find_ffmpeg().await.map(|(_, version)| version)
+}
+
+#[tauri::command]
+async fn get_ytdlp_version(app: AppHandle) -> Result<String, AppError> {
+    let bin_path = resolve_ytdlp_path(&app).await?;
+
+    let output = Command::new(&bin_path)
+        .arg("--version")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(AppError::YtDlpFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[derive(Debug, Serialize)]
+struct AppInfo {
+    app_version: String,
+    /// `None` when yt-dlp is missing or fails to run.
+    ytdlp_version: Option<String>,
+    /// First line of `ffmpeg -version`, or `None` when ffmpeg isn't available.
+    ffmpeg_version: Option<String>,
+    /// Operating system, e.g. `windows`, `macos` or `linux`.
+    platform: String,
+}
+
+/// Collects the versions and environment shown in the About dialog.
+#[tauri::command]
+async fn get_app_info(app: AppHandle) -> AppInfo {
+    AppInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        ytdlp_version: get_ytdlp_version(app).await.ok(),
+        ffmpeg_version: find_ffmpeg().await.ok().map(|(_, version)| version),
+        platform: std::env::consts::OS.to_string(),
+    }
+}
+
+/// Trims the URL and rejects anything yt-dlp could mistake for an option or
+/// that isn't an http(s) link.
+fn validate_url(url: &str) -> Result<String, AppError> {
+    let url = url.trim();
+    if url.is_empty() {
+        return Err(AppError::InvalidInput("URL is empty".to_string()));
+    }
+    if url.starts_with('-') {
+        return Err(AppError::InvalidInput(format!("Invalid URL: {}", url)));
+    }
+
+    let scheme = url
+        .split_once("://")
+        .map(|(scheme, _)| scheme.to_lowercase());
+    if !matches!(scheme.as_deref(), Some("http") | Some("https")) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid URL: {} (expected an http or https link)",
+            url
+        )));
+    }
+
+    Ok(url.to_string())
+}
+
+/// Sent about once a second while yt-dlp extracts video info, and for each
+/// line it prints to stderr.
+#[derive(Clone, Serialize)]
+struct InfoProgress {
+    url: String,
+    elapsed_seconds: u64,
+    message: Option<String>,
+}
+
+/// Fills in the proxy from the settings when the caller didn't set one. Like
+/// `download_media`, unreadable settings only log and leave it unset.
+async fn with_saved_proxy(app: &AppHandle, options: Option<FetchOptions>) -> FetchOptions {
+    let mut options = options.unwrap_or_default();
+    if options.proxy.is_none() {
+        match settings::load(app, &app.state::<SettingsStore>()).await {
+            Ok(saved) => options.proxy = saved.proxy,
+            Err(e) => println!("Failed to load settings: {}", e),
+        }
+    }
+    options
+}
+
+/// yt-dlp processes of running info requests, keyed by info id.
+#[derive(Default)]
+struct InfoRegistry {
+    children: Mutex<HashMap<String, Child>>,
+    /// Requests waiting to retry, woken by `cancel_video_info`.
+    retrying: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+/// How long info commands wait for yt-dlp by default.
+const DEFAULT_INFO_TIMEOUT_SECS: u64 = 60;
+
+/// Runs `yt-dlp --dump-json` for a single video and parses its output,
+/// emitting `info-progress` events until it finishes. yt-dlp is killed if it
+/// takes longer than `timeout_seconds`. The process is registered under
+/// `info_id`, or a generated id, so `cancel_video_info` can stop it.
+async fn dump_video_json(
+    app: &AppHandle,
+    url: &str,
+    info_id: Option<String>,
+    options: Option<FetchOptions>,
+    timeout_seconds: Option<u64>,
+) -> Result<serde_json::Value, AppError> {
+    let bin_path = resolve_ytdlp_path(app).await?;
+    let options = with_saved_proxy(app, options).await;
+    let fetch_args = options.to_args()?;
+
+    let info_id = info_id.unwrap_or_else(|| new_id("info"));
+    let infos = app.state::<InfoRegistry>();
+    if infos.children.lock().await.contains_key(&info_id) {
+        return Err(AppError::InvalidInput(format!(
+            "An info request with id {} is already running",
+            info_id
+        )));
+    }
+
+    // Use --dump-json to get video metadata
+    let mut child = Command::new(&bin_path)
+        .args(["--dump-json", "--no-playlist"])
+        .args(&fetch_args)
+        .arg(url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stdout".to_string()))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stderr".to_string()))?;
+    infos.children.lock().await.insert(info_id.clone(), child);
+
+    // The JSON arrives in one piece at the end, so collect it in the background
+    let stdout_task = tokio::spawn(async move {
+        let mut json = Vec::new();
+        stdout.read_to_end(&mut json).await.map(|_| json)
+    });
+
+    let timeout_seconds = timeout_seconds.unwrap_or(DEFAULT_INFO_TIMEOUT_SECS);
+    let deadline = tokio::time::sleep(Duration::from_secs(timeout_seconds));
+    tokio::pin!(deadline);
+
+    let started = Instant::now();
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(1));
+    let mut stderr_reader = BufReader::new(stderr).lines();
+    let mut stderr_output = String::new();
+    loop {
+        let message = tokio::select! {
+            _ = &mut deadline => {
+                println!("yt-dlp timed out after {}s fetching {}", timeout_seconds, url);
+                if let Some(mut child) = infos.children.lock().await.remove(&info_id) {
+                    let _ = child.start_kill();
+                }
+                return Err(AppError::Timeout {
+                    seconds: timeout_seconds,
+                });
+            }
+            _ = heartbeat.tick() => None,
+            line = stderr_reader.next_line() => match line {
+                Ok(Some(line)) => {
+                    stderr_output.push_str(&line);
+                    stderr_output.push('\n');
+                    Some(line)
+                }
+                _ => break,
+            },
+        };
+        let _ = app.emit(
+            "info-progress",
+            InfoProgress {
+                url: url.to_string(),
+                elapsed_seconds: started.elapsed().as_secs(),
+                message,
+            },
+        );
+    }
+
+    // Removing the child from the registry tells us it was cancelled
+    let Some(mut child) = infos.children.lock().await.remove(&info_id) else {
+        println!("Info request {} was cancelled", info_id);
+        return Err(AppError::Cancelled);
+    };
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to wait on child: {}", e)))?;
+    let json = stdout_task
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .map_err(|e| AppError::Io(format!("Failed to read yt-dlp output: {}", e)))?;
+
+    if !status.success() {
+        if let Some(proxy) = options.proxy.filter(|_| is_proxy_error(&stderr_output)) {
+            return Err(AppError::ProxyFailed {
+                proxy,
+                stderr: stderr_output,
+            });
+        }
+        if let Some(error) = AppError::from_ytdlp_stderr(&stderr_output) {
+            return Err(error);
+        }
+        return Err(AppError::YtDlpFailed {
+            code: status.code(),
+            stderr: stderr_output,
+        });
+    }
+
+    // Parse JSON response. A playlist URL prints one object per video.
+    let mut values = serde_json::Deserializer::from_slice(&json).into_iter::<serde_json::Value>();
+    let parsed = values
+        .next()
+        .ok_or_else(|| AppError::JsonParse("yt-dlp printed no video info".to_string()))?
+        .map_err(|e| AppError::JsonParse(e.to_string()))?;
+    if values.next().is_some() || parsed["_type"] == "playlist" {
+        return Err(AppError::NoFormats(
+            "This URL points at a playlist rather than a single video".to_string(),
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Stops a running `get_video_info` call started with `info_id`, which then
+/// fails with `AppError::Cancelled`.
+#[tauri::command]
+async fn cancel_video_info(
+    infos: State<'_, InfoRegistry>,
+    info_id: String,
+) -> Result<(), AppError> {
+    println!("Cancelling info request: {}", info_id);
+
+    let child = infos.children.lock().await.remove(&info_id);
+    if let Some(mut child) = child {
+        return child
+            .kill()
+            .await
+            .map_err(|e| AppError::Io(format!("Failed to kill yt-dlp: {}", e)));
+    }
+
+    // Between attempts there's no process, so wake the retry wait instead
+    match infos.retrying.lock().await.remove(&info_id) {
+        Some(retry) => {
+            retry.notify_one();
+            Ok(())
+        }
+        None => Err(AppError::InvalidInput(format!(
+            "No active info request with id: {}",
+            info_id
+        ))),
+    }
+}
+
+/// Retries `get_video_info` makes by default after a transient failure.
+const DEFAULT_INFO_RETRIES: u32 = 3;
+
+/// Whether a failed info fetch looks like a network hiccup worth retrying,
+/// such as a connection reset or an HTTP 403, 429 or 5xx. Our own
+/// `AppError::Timeout` isn't retried, since a dead link would then take
+/// several times `timeout_seconds` to fail.
+fn is_transient_error(error: &AppError) -> bool {
+    let stderr = match error {
+        AppError::YtDlpFailed { stderr, .. } => stderr,
+        _ => return false,
+    };
+    let Ok(http_regex) = Regex::new(r"HTTP Error (?:403|429|5\d\d)") else {
+        return false;
+    };
+    http_regex.is_match(stderr)
+        || [
+            "timed out",
+            "Connection reset",
+            "Temporary failure in name resolution",
+        ]
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+}
+
+#[tauri::command]
+async fn get_video_info(
+    app: AppHandle,
+    url: String,
+    info_id: Option<String>,
+    options: Option<FetchOptions>,
+    timeout_seconds: Option<u64>,
+    retries: Option<u32>,
+) -> Result<VideoInfo, AppError> {
+    let url = validate_url(&url)?;
+    println!("Fetching video info for: {}", url);
+
+    let retries = retries.unwrap_or(DEFAULT_INFO_RETRIES);
+    // Shared by every attempt so cancel_video_info works throughout
+    let info_id = info_id.unwrap_or_else(|| new_id("info"));
+    let infos = app.state::<InfoRegistry>();
+    let mut attempt = 0;
+    let parsed = loop {
+        match dump_video_json(
+            &app,
+            &url,
+            Some(info_id.clone()),
+            options.clone(),
+            timeout_seconds,
+        )
+        .await
+        {
+            Err(e) if attempt < retries && is_transient_error(&e) => {
+                attempt += 1;
+                let delay = Duration::from_secs(1 << (attempt - 1).min(5));
+                println!(
+                    "Info fetch failed ({}), retry {}/{} in {:?}",
+                    e, attempt, retries, delay
+                );
+                let retry = Arc::new(Notify::new());
+                infos
+                    .retrying
+                    .lock()
+                    .await
+                    .insert(info_id.clone(), retry.clone());
+                tokio::select! {
+                    // Cancelled just as the wait ended if the entry is already gone
+                    _ = tokio::time::sleep(delay) => {
+                        if infos.retrying.lock().await.remove(&info_id).is_none() {
+                            return Err(AppError::Cancelled);
+                        }
+                    }
+                    _ = retry.notified() => {
+                        println!("Info request {} was cancelled", info_id);
+                        return Err(AppError::Cancelled);
+                    }
+                }
+            }
+            result => break result?,
+        }
+    };
+    // Without formats the best/worst options below would only fail at download time
+    if parsed["formats"]
+        .as_array()
+        .is_none_or(|formats| formats.is_empty())
+    {
+        return Err(AppError::NoFormats(
+            "yt-dlp found no downloadable formats for this video".to_string(),
+        ));
+    }
 
     let mut quality_options = Vec::new();
 
@@ -67,18 +960,39 @@ async fn get_video_info(url: String) -> Result<Vec<QualityOption>, String> {
         id: "best".to_string(),
         label: "Best Available".to_string(),
         format_type: "default".to_string(),
+        filesize_bytes: None,
+        fps: None,
+        dynamic_range: None,
+        abr: None,
+        requires_merge: true,
     });
 
     quality_options.push(QualityOption {
         id: "worst".to_string(),
         label: "Lowest Available".to_string(),
         format_type: "default".to_string(),
+        filesize_bytes: None,
+        fps: None,
+        dynamic_range: None,
+        abr: None,
+        requires_merge: true,
     });
 
     // Extract formats array
     if let Some(formats) = parsed["formats"].as_array() {
         let mut seen_resolutions = std::collections::HashSet::new();
 
+        // Formats are sorted worst to best, so the last audio-only entry is the
+        // one "+ba" merges with video-only selections
+        let best_audio_size = formats
+            .iter()
+            .rev()
+            .find(|format| {
+                format["vcodec"].as_str().unwrap_or("none") == "none"
+                    && format["acodec"].as_str().unwrap_or("none") != "none"
+            })
+            .and_then(format_filesize);
+
         for format in formats.iter().rev() {
             // Reverse to get best qualities first
             let vcodec = format["vcodec"].as_str().unwrap_or("none");
@@ -86,6 +1000,9 @@ async fn get_video_info(url: String) -> Result<Vec<QualityOption>, String> {
             let height = format["height"].as_i64();
             let format_id = format["format_id"].as_str().unwrap_or("");
             let ext = format["ext"].as_str().unwrap_or("mp4");
+            let filesize = format_filesize(format);
+            let fps = format["fps"].as_f64().map(|fps| fps.round() as u32);
+            let dynamic_range = format["dynamic_range"].as_str();
 
             // Skip if no video or audio
             if vcodec == "none" && acodec == "none" {
@@ -96,21 +1013,38 @@ async fn get_video_info(url: String) -> Result<Vec<QualityOption>, String> {
             let has_video = vcodec != "none";
             let has_audio = acodec != "none";
 
+            // e.g. "1080p", "1080p60" or "2160p60 HDR10", which also keeps 30fps,
+            // 60fps and HDR variants of the same height apart when deduplicating
+            let resolution = height.map(|h| {
+                let mut resolution = format!("{}p", h);
+                if let Some(fps) = fps.filter(|&fps| fps > 30) {
+                    resolution.push_str(&fps.to_string());
+                }
+                if let Some(range) = dynamic_range.filter(|&range| range != "SDR") {
+                    resolution.push(' ');
+                    resolution.push_str(range);
+                }
+                resolution
+            });
+
             if has_video && has_audio {
-                if let Some(h) = height {
-                    let resolution = format!("{}p", h);
+                if let Some(resolution) = resolution {
                     if !seen_resolutions.contains(&resolution) {
                         seen_resolutions.insert(resolution.clone());
                         quality_options.push(QualityOption {
                             id: format_id.to_string(),
                             label: format!("{} ({})", resolution, ext),
                             format_type: "video+audio".to_string(),
+                            filesize_bytes: filesize,
+                            fps,
+                            dynamic_range: dynamic_range.map(str::to_string),
+                            abr: format["abr"].as_f64(),
+                            requires_merge: false,
                         });
                     }
                 }
             } else if has_video {
-                if let Some(h) = height {
-                    let resolution = format!("{}p", h);
+                if let Some(resolution) = resolution {
                     let key = format!("{}-video", resolution);
                     if !seen_resolutions.contains(&key) {
                         seen_resolutions.insert(key);
@@ -118,123 +1052,2016 @@ async fn get_video_info(url: String) -> Result<Vec<QualityOption>, String> {
                             id: format_id.to_string(),
                             label: format!("{} (video only)", resolution),
                             format_type: "video".to_string(),
+                            filesize_bytes: filesize
+                                .map(|size| size + best_audio_size.unwrap_or(0)),
+                            fps,
+                            dynamic_range: dynamic_range.map(str::to_string),
+                            abr: None,
+                            requires_merge: true,
                         });
                     }
                 }
             } else if has_audio {
-                let key = format!("audio-{}", format_id);
+                // "mp4a.40.2" -> "mp4a", so the same track in different
+                // profiles isn't listed twice
+                let codec = acodec.split('.').next().unwrap_or(acodec);
+                let abr = format["abr"].as_f64();
+                let key = format!(
+                    "audio-{}-{}",
+                    codec,
+                    abr.map_or(0, |abr| abr.round() as u32)
+                );
                 if !seen_resolutions.contains(&key) {
                     seen_resolutions.insert(key);
+                    let label = match abr {
+                        Some(abr) => format!("Audio only ({}, {:.0} kbps)", ext, abr),
+                        None => format!("Audio only ({})", ext),
+                    };
                     quality_options.push(QualityOption {
                         id: format_id.to_string(),
-                        label: format!("Audio only ({})", ext),
+                        label,
                         format_type: "audio".to_string(),
+                        filesize_bytes: filesize,
+                        fps: None,
+                        dynamic_range: None,
+                        abr,
+                        requires_merge: false,
                     });
-                    break; // Only add one audio option
                 }
             }
         }
     }
 
-    Ok(quality_options)
+    Ok(VideoInfo {
+        title: parsed["title"].as_str().map(str::to_string),
+        duration_seconds: parsed["duration"].as_f64(),
+        thumbnail_url: parsed["thumbnail"].as_str().map(str::to_string),
+        uploader: parsed["uploader"].as_str().map(str::to_string),
+        view_count: parsed["view_count"].as_u64(),
+        qualities: quality_options,
+    })
 }
 
-#[derive(Clone, Serialize)]
-struct LogMessage {
-    message_type: String,
-    message: String,
+#[derive(Debug, Serialize)]
+struct PlaylistEntry {
+    id: Option<String>,
+    title: Option<String>,
 }
 
-#[derive(Clone, Serialize)]
-struct DownloadProgress {
-    progress: f64,
-    status: String,
+#[derive(Debug, Serialize)]
+struct PlaylistInfo {
+    title: Option<String>,
+    entry_count: usize,
+    entries: Vec<PlaylistEntry>,
 }
 
+/// Lists a playlist's entries without extracting each video, so a playlist
+/// download can show its total up front.
 #[tauri::command]
-async fn download_media(
-    window: Window,
+async fn get_playlist_info(
+    app: AppHandle,
     url: String,
-    format: String,
-    quality: String,
-    download_path: Option<String>,
-) -> Result<String, String> {
-    println!(
-        "Downloading: {} (Format: {}, Quality: {}, Path: {:?})",
-        url, format, quality, download_path
-    );
+    options: Option<FetchOptions>,
+    timeout_seconds: Option<u64>,
+) -> Result<PlaylistInfo, AppError> {
+    let url = validate_url(&url)?;
+    println!("Fetching playlist info for: {}", url);
 
-    // Emit initial log to frontend
-    let _ = window.emit(
-        "download-log",
-        LogMessage {
-            message_type: "stdout".to_string(),
-            message: format!(
-                "Starting download... URL: {}, Path: {:?}",
-                url, download_path
-            ),
-        },
-    );
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, options).await.to_args()?;
 
-    let bin_path = Path::new("bin/yt-dlp.exe");
-    println!("Checking for yt-dlp.exe at: {:?}", bin_path);
-    println!("Current dir: {:?}", std::env::current_dir());
-    if !bin_path.exists() {
-        let err_msg = format!(
-            "yt-dlp.exe not found in bin directory. Current dir: {:?}, Checked path: {:?}",
-            std::env::current_dir(),
-            bin_path
-        );
-        println!("{}", err_msg);
-        return Err(err_msg);
-    }
-    println!("yt-dlp.exe found, building args...");
+    let mut args = vec!["--flat-playlist", "--dump-single-json", "--yes-playlist"];
+    args.extend(fetch_args.iter().map(String::as_str));
+    args.push(&url);
+    let stdout = run_ytdlp(&bin_path, &args, timeout_seconds).await?;
 
-    let mut args = Vec::new();
-    args.push(url.clone());
-    args.push("--newline".to_string()); // Ensure line-buffered output
-    args.push("--progress".to_string()); // Force progress output
+    let parsed: serde_json::Value =
+        serde_json::from_str(&stdout).map_err(|e| AppError::JsonParse(e.to_string()))?;
+    // A single video has no entries, and counts as a playlist of one
+    let entries: Vec<PlaylistEntry> = match parsed["entries"].as_array() {
+        Some(entries) => entries
+            .iter()
+            .map(|entry| PlaylistEntry {
+                id: entry["id"].as_str().map(str::to_string),
+                title: entry["title"].as_str().map(str::to_string),
+            })
+            .collect(),
+        None => vec![PlaylistEntry {
+            id: parsed["id"].as_str().map(str::to_string),
+            title: parsed["title"].as_str().map(str::to_string),
+        }],
+    };
 
-    // Output template to Downloads folder or current dir
+    Ok(PlaylistInfo {
+        title: parsed["title"].as_str().map(str::to_string),
+        entry_count: entries.len(),
+        entries,
+    })
+}
+
+/// Lists every format yt-dlp reports for a video, without the deduplication
+/// `get_video_info` applies. Any `format_id` can be passed to `download_media`
+/// as the quality.
+#[tauri::command]
+async fn get_raw_formats(
+    app: AppHandle,
+    url: String,
+    options: Option<FetchOptions>,
+    timeout_seconds: Option<u64>,
+) -> Result<Vec<VideoFormat>, AppError> {
+    let url = validate_url(&url)?;
+    println!("Fetching raw formats for: {}", url);
+
+    let parsed = dump_video_json(&app, &url, None, options, timeout_seconds).await?;
+    let Some(formats) = parsed["formats"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    formats
+        .iter()
+        .map(|format| {
+            serde_json::from_value(format.clone()).map_err(|e| AppError::JsonParse(e.to_string()))
+        })
+        .collect()
+}
+
+/// A chapter as listed in yt-dlp's `chapters` array, with times in seconds.
+#[derive(Debug, Serialize, Deserialize)]
+struct Chapter {
+    #[serde(default)]
+    title: String,
+    start_time: f64,
+    end_time: f64,
+}
+
+/// Lists a video's chapters, e.g. to pick one to download with `start_time`
+/// and `end_time`. Empty when the video has none.
+#[tauri::command]
+async fn get_chapters(
+    app: AppHandle,
+    url: String,
+    options: Option<FetchOptions>,
+    timeout_seconds: Option<u64>,
+) -> Result<Vec<Chapter>, AppError> {
+    let url = validate_url(&url)?;
+    println!("Fetching chapters for: {}", url);
+
+    let parsed = dump_video_json(&app, &url, None, options, timeout_seconds).await?;
+    // yt-dlp reports null rather than an empty array for videos without chapters
+    let Some(chapters) = parsed["chapters"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    chapters
+        .iter()
+        .map(|chapter| {
+            serde_json::from_value(chapter.clone()).map_err(|e| AppError::JsonParse(e.to_string()))
+        })
+        .collect()
+}
+
+/// How long `probe_url` waits for yt-dlp by default.
+const DEFAULT_PROBE_TIMEOUT_SECS: u64 = 15;
+
+/// Quickly checks whether yt-dlp can extract a video from the URL, without
+/// fetching the full info. Dead links and timeouts count as not fetchable.
+#[tauri::command]
+async fn probe_url(
+    app: AppHandle,
+    url: String,
+    timeout_seconds: Option<u64>,
+    options: Option<FetchOptions>,
+) -> Result<bool, AppError> {
+    let url = validate_url(&url)?;
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, options).await.to_args()?;
+
+    let output = Command::new(&bin_path)
+        .args(["--simulate", "--quiet", "--no-warnings", "--no-playlist"])
+        .args(&fetch_args)
+        .arg(&url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Dropping the future on timeout then kills yt-dlp
+        .kill_on_drop(true)
+        .output();
+
+    let timeout = Duration::from_secs(timeout_seconds.unwrap_or(DEFAULT_PROBE_TIMEOUT_SECS));
+    match tokio::time::timeout(timeout, output).await {
+        Ok(output) => {
+            let output = output.map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+            Ok(output.status.success())
+        }
+        Err(_) => {
+            println!("Probing {} timed out after {:?}", url, timeout);
+            Ok(false)
+        }
+    }
+}
+
+/// A video thumbnail, base64-encoded so the webview can show it as a data URL.
+#[derive(Debug, Serialize)]
+struct Thumbnail {
+    mime_type: String,
+    data: String,
+}
+
+/// Largest download `download_to_buffer` keeps in memory.
+const MAX_BUFFER_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Downloads the media straight into memory instead of a file, for features
+/// that process it in place. Returned as raw bytes, an `ArrayBuffer` on the
+/// frontend. yt-dlp is killed once the media grows past `MAX_BUFFER_BYTES`.
+#[tauri::command]
+async fn download_to_buffer(
+    app: AppHandle,
+    url: String,
+    quality: String,
+    format: String,
+) -> Result<tauri::ipc::Response, AppError> {
+    let url = validate_url(&url)?;
+    println!("Downloading {} into memory ({}, {})", url, format, quality);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, None).await.to_args()?;
+    let mut child = Command::new(&bin_path)
+        .args(["--no-playlist", "--quiet", "-o", "-", "-f"])
+        .arg(format_selector(&format, &quality))
+        .args(&fetch_args)
+        .arg(&url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stdout".to_string()))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stderr".to_string()))?;
+    let stderr_task = tokio::spawn(async move {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output).await;
+        output
+    });
+
+    // Read one byte past the limit to tell a full buffer from an oversized one
+    let mut data = Vec::new();
+    stdout
+        .take(MAX_BUFFER_BYTES + 1)
+        .read_to_end(&mut data)
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to read yt-dlp output: {}", e)))?;
+    if data.len() as u64 > MAX_BUFFER_BYTES {
+        let _ = child.kill().await;
+        return Err(AppError::BufferLimitExceeded {
+            limit: MAX_BUFFER_BYTES,
+        });
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to wait on child: {}", e)))?;
+    if !status.success() {
+        let stderr = stderr_task.await.unwrap_or_default();
+        if let Some(error) = AppError::from_ytdlp_stderr(&stderr) {
+            return Err(error);
+        }
+        return Err(AppError::YtDlpFailed {
+            code: status.code(),
+            stderr,
+        });
+    }
+
+    Ok(tauri::ipc::Response::new(data))
+}
+
+/// Downloads a video's thumbnail through yt-dlp, so it honors the same proxy
+/// and cookies as the video and avoids CORS issues in the webview.
+#[tauri::command]
+async fn get_thumbnail(
+    app: AppHandle,
+    url: String,
+    options: Option<FetchOptions>,
+) -> Result<Thumbnail, AppError> {
+    let url = validate_url(&url)?;
+    println!("Fetching thumbnail for: {}", url);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, options).await.to_args()?;
+
+    let dir = std::env::temp_dir().join(format!("frieren-{}", new_id("thumbnail")));
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Io(format!("Failed to create temp directory: {}", e)))?;
+    let dir_arg = dir.to_string_lossy().into_owned();
+
+    let mut args = vec![
+        "--skip-download",
+        "--write-thumbnail",
+        "--no-playlist",
+        "-P",
+        &dir_arg,
+        "-o",
+        "thumbnail.%(ext)s",
+    ];
+    args.extend(fetch_args.iter().map(String::as_str));
+    args.push(&url);
+
+    let result = run_ytdlp(&bin_path, &args, None).await.and_then(|_| {
+        let path = std::fs::read_dir(&dir)
+            .map_err(|e| AppError::Io(format!("Failed to read temp directory: {}", e)))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|path| path.is_file())
+            .ok_or_else(|| AppError::Io("yt-dlp didn't write a thumbnail".to_string()))?;
+
+        let mime_type = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("webp") => "image/webp",
+            Some("png") => "image/png",
+            _ => "application/octet-stream",
+        };
+        let bytes = std::fs::read(&path)
+            .map_err(|e| AppError::Io(format!("Failed to read thumbnail: {}", e)))?;
+        Ok(Thumbnail {
+            mime_type: mime_type.to_string(),
+            data: BASE64_STANDARD.encode(bytes),
+        })
+    });
+
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// A long-lived public video used to check that a proxy works.
+const PROXY_TEST_URL: &str = "https://www.youtube.com/watch?v=jNQXAC9IVRw";
+
+/// Checks that yt-dlp can reach YouTube through `proxy`.
+#[tauri::command]
+async fn test_proxy(app: AppHandle, proxy: String) -> Result<(), AppError> {
+    println!("Testing proxy: {}", proxy);
+
+    let options = FetchOptions {
+        proxy: Some(proxy.clone()),
+        ..Default::default()
+    };
+    let fetch_args = options.to_args()?;
+    let bin_path = resolve_ytdlp_path(&app).await?;
+
+    let mut args = vec!["--simulate", "--quiet", "--no-warnings", "--no-playlist"];
+    args.extend(fetch_args.iter().map(String::as_str));
+    args.push(PROXY_TEST_URL);
+
+    match run_ytdlp(&bin_path, &args, Some(DEFAULT_PROBE_TIMEOUT_SECS)).await {
+        Ok(_) => Ok(()),
+        Err(AppError::YtDlpFailed { stderr, .. }) => Err(AppError::ProxyFailed { proxy, stderr }),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SubtitleTrack {
+    language: String,
+    name: Option<String>,
+    formats: Vec<String>,
+    automatic: bool,
+}
+
+/// Runs yt-dlp to completion and returns its stdout, killing it after
+/// `timeout_seconds` (`DEFAULT_INFO_TIMEOUT_SECS` if unset).
+async fn run_ytdlp(
+    bin_path: &Path,
+    args: &[&str],
+    timeout_seconds: Option<u64>,
+) -> Result<String, AppError> {
+    let output = Command::new(bin_path)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Dropping the future on timeout then kills yt-dlp
+        .kill_on_drop(true)
+        .output();
+
+    let timeout_seconds = timeout_seconds.unwrap_or(DEFAULT_INFO_TIMEOUT_SECS);
+    let output = tokio::time::timeout(Duration::from_secs(timeout_seconds), output)
+        .await
+        .map_err(|_| AppError::Timeout {
+            seconds: timeout_seconds,
+        })?
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        if let Some(error) = AppError::from_ytdlp_stderr(&stderr) {
+            return Err(error);
+        }
+        return Err(AppError::YtDlpFailed {
+            code: output.status.code(),
+            stderr,
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The `-f` selector `build_download_args` effectively uses for a format and
+/// quality, ignoring the optional filters. Audio extraction picks `ba/b`.
+fn format_selector(format: &str, quality: &str) -> String {
+    let is_audio = format == "audio" || format == "audio_only";
+    let is_video_audio = format == "video+audio" || format == "video_audio";
+    match quality {
+        "best" if is_audio => "ba/b".to_string(),
+        "worst" if is_audio => "wa/w".to_string(),
+        "best" if is_video_audio => "bv+ba/b".to_string(),
+        "worst" if is_video_audio => "wv+wa/w".to_string(),
+        "best" => "bv".to_string(),
+        "worst" => "wv".to_string(),
+        _ if is_audio => format!("{}[vcodec=none]/ba", quality),
+        _ if is_video_audio => format!("{}+ba/b", quality),
+        _ => quality.to_string(),
+    }
+}
+
+/// Estimates how many bytes downloading `url` with this format and quality
+/// would fetch, adding up the streams yt-dlp would merge. `None` when a size
+/// isn't known, as is common for livestreams.
+#[tauri::command]
+async fn estimate_size(
+    app: AppHandle,
+    url: String,
+    quality: String,
+    format: String,
+) -> Result<Option<u64>, AppError> {
+    let url = validate_url(&url)?;
+    println!("Estimating size of {} ({}, {})", url, format, quality);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, None).await.to_args()?;
+    let selector = format_selector(&format, &quality);
+    let mut args = vec!["--dump-json", "--no-playlist", "-f", &selector];
+    args.extend(fetch_args.iter().map(String::as_str));
+    args.push(&url);
+
+    let output = run_ytdlp(&bin_path, &args, None).await?;
+    let parsed: serde_json::Value =
+        serde_json::from_str(&output).map_err(|e| AppError::JsonParse(e.to_string()))?;
+
+    // Merged selections list their streams under "requested_formats"
+    match parsed["requested_formats"].as_array() {
+        Some(formats) => Ok(formats.iter().map(format_filesize).sum::<Option<u64>>()),
+        None => Ok(format_filesize(&parsed)),
+    }
+}
+
+/// Returns the file name a download would be saved as, expanded and
+/// sanitized by yt-dlp itself. For a playlist this is the first entry's.
+#[tauri::command]
+async fn get_output_filename(
+    app: AppHandle,
+    url: String,
+    output_template: Option<String>,
+) -> Result<String, AppError> {
+    let url = validate_url(&url)?;
+    let template = output_template.unwrap_or_else(|| DEFAULT_OUTPUT_TEMPLATE.to_string());
+    validate_output_template(&template)?;
+    println!("Resolving output filename for {} with {}", url, template);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let fetch_args = with_saved_proxy(&app, None).await.to_args()?;
+    // Same playlist handling and filename trimming as the download itself
+    let request = DownloadRequest {
+        url: url.clone(),
+        output_template: Some(template),
+        ..Default::default()
+    };
+    let mut args = vec![
+        "--print",
+        "filename",
+        "--skip-download",
+        "--playlist-items",
+        "1",
+    ];
+    let location_args = location_args(&request, None);
+    args.extend(location_args.iter().map(String::as_str));
+    args.extend(fetch_args.iter().map(String::as_str));
+    args.push(&url);
+
+    let output = run_ytdlp(&bin_path, &args, None).await?;
+    output
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .ok_or_else(|| AppError::YtDlpFailed {
+            code: Some(0),
+            stderr: "yt-dlp didn't print a filename".to_string(),
+        })
+}
+
+/// Returns yt-dlp's `-F` format table as printed, for picking a format ID by hand.
+#[tauri::command]
+async fn list_formats_text(
+    app: AppHandle,
+    url: String,
+    timeout_seconds: Option<u64>,
+) -> Result<String, AppError> {
+    let url = validate_url(&url)?;
+    println!("Listing formats for: {}", url);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    run_ytdlp(&bin_path, &["-F", "--no-playlist", &url], timeout_seconds).await
+}
+
+/// Extractor names from `--list-extractors`, fetched once per session.
+#[derive(Default)]
+struct ExtractorCache {
+    extractors: Mutex<Option<Vec<String>>>,
+}
+
+/// Lists the extractors yt-dlp supports, e.g. `youtube` or `vimeo`. Broken
+/// ones keep yt-dlp's "(CURRENTLY BROKEN)" suffix.
+#[tauri::command]
+async fn list_extractors(
+    app: AppHandle,
+    cache: State<'_, ExtractorCache>,
+) -> Result<Vec<String>, AppError> {
+    let mut extractors = cache.extractors.lock().await;
+    if let Some(extractors) = extractors.as_ref() {
+        return Ok(extractors.clone());
+    }
+
+    println!("Listing extractors");
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let output = run_ytdlp(&bin_path, &["--list-extractors"], None).await?;
+    let list: Vec<String> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    *extractors = Some(list.clone());
+    Ok(list)
+}
+
+#[tauri::command]
+async fn list_subtitles(
+    app: AppHandle,
+    url: String,
+    timeout_seconds: Option<u64>,
+) -> Result<Vec<SubtitleTrack>, AppError> {
+    let url = validate_url(&url)?;
+    println!("Listing subtitles for: {}", url);
+
+    let bin_path = resolve_ytdlp_path(&app).await?;
+
+    let stdout = run_ytdlp(
+        &bin_path,
+        &["--list-subs", "--skip-download", "--no-playlist", &url],
+        timeout_seconds,
+    )
+    .await?;
+
+    parse_subtitle_list(&stdout)
+}
+
+/// Parses the tables printed by `yt-dlp --list-subs`.
+fn parse_subtitle_list(stdout: &str) -> Result<Vec<SubtitleTrack>, AppError> {
+    // Rows look like "en-US    English (United States)   vtt, ttml, srv3"
+    // (older yt-dlp versions omit the name column)
+    let row_regex = Regex::new(r"^(\S+)\s+(?:(.*?)\s+)?((?:[\w-]+,\s*)*[\w-]+)$")?;
+    // e.g. "dQw4w9WgXcQ has no subtitles", which would otherwise parse as a row
+    let missing_regex = Regex::new(r"has no (subtitles|automatic captions)$")?;
+
+    let mut tracks = Vec::new();
+    let mut automatic = false;
+
+    for line in stdout.lines() {
+        let line = line.trim();
+        if line.starts_with("[info] Available automatic captions") {
+            automatic = true;
+            continue;
+        }
+        if line.starts_with("[info] Available subtitles") {
+            automatic = false;
+            continue;
+        }
+        if line.is_empty()
+            || line.starts_with('[')
+            || line.starts_with("Language")
+            || missing_regex.is_match(line)
+        {
+            continue;
+        }
+
+        if let Some(caps) = row_regex.captures(line) {
+            tracks.push(SubtitleTrack {
+                language: caps[1].to_string(),
+                name: caps.get(2).map(|m| m.as_str().trim().to_string()),
+                formats: caps[3].split(',').map(|f| f.trim().to_string()).collect(),
+                automatic,
+            });
+        }
+    }
+
+    Ok(tracks)
+}
+
+#[derive(Clone, Serialize)]
+struct LogMessage {
+    /// Download the line belongs to, `None` for logs from other commands.
+    download_id: Option<String>,
+    /// `stdout`, or for stderr lines `warning`, `error` or plain `stderr`.
+    message_type: String,
+    message: String,
+}
+
+/// Classifies a stderr line by yt-dlp's `WARNING:`/`ERROR:` prefix.
+fn stderr_message_type(line: &str) -> &'static str {
+    if line.starts_with("ERROR:") {
+        "error"
+    } else if line.starts_with("WARNING:") {
+        "warning"
+    } else {
+        "stderr"
+    }
+}
+
+#[derive(Clone, Default, Serialize)]
+struct DownloadProgress {
+    download_id: String,
+    progress: f64,
+    status: String,
+    speed: Option<String>,
+    eta: Option<String>,
+    total_size: Option<String>,
+    /// Bytes of the current file downloaded so far, estimated from the percentage.
+    downloaded_bytes: Option<u64>,
+    /// `total_size` in bytes.
+    total_bytes: Option<u64>,
+    /// Human-readable description of the current step, e.g. "Merging formats".
+    detail: Option<String>,
+    /// Fragment position for DASH/HLS downloads, from yt-dlp's "(frag N/M)".
+    fragment_index: Option<u32>,
+    fragment_count: Option<u32>,
+    /// "video" or "audio" while downloading the streams of a merged format,
+    /// "merge" while combining them. `progress` then covers all streams.
+    phase: Option<String>,
+    /// How long the current post-processing step has been running. yt-dlp
+    /// doesn't report ffmpeg's progress, so it's resent every second instead.
+    elapsed_seconds: Option<u64>,
+}
+
+/// Parses a size as printed by yt-dlp, e.g. "10.50MiB" or "1.2GB", into bytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let unit_start = size.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, unit) = size.split_at(unit_start);
+    let number: f64 = number.trim().parse().ok()?;
+    let multiplier: f64 = match unit {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        "KB" | "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((number * multiplier) as u64)
+}
+
+/// Formats a byte count the way yt-dlp does, e.g. "1.50GiB".
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{:.2}{}", size, unit);
+        }
+        size /= 1024.0;
+    }
+    format!("{:.2}TiB", size)
+}
+
+/// Describes the yt-dlp post-processor that printed a `[Name]` prefixed line,
+/// or returns `None` if the prefix isn't a post-processing step.
+fn postprocessor_description(name: &str) -> Option<&'static str> {
+    match name {
+        "Merger" => Some("Merging formats"),
+        "ExtractAudio" => Some("Extracting audio"),
+        "VideoConvertor" => Some("Converting video"),
+        "VideoRemuxer" => Some("Remuxing video"),
+        "EmbedSubtitle" => Some("Embedding subtitles"),
+        "EmbedThumbnail" => Some("Embedding thumbnail"),
+        "ThumbnailsConvertor" => Some("Converting thumbnail"),
+        "Metadata" => Some("Writing metadata"),
+        "ModifyChapters" => Some("Modifying chapters"),
+        "SplitChapters" => Some("Splitting chapters"),
+        name if name.starts_with("Fixup") => Some("Fixing up file"),
+        _ => None,
+    }
+}
+
+/// Running yt-dlp processes keyed by the download id supplied by the frontend.
+#[derive(Default)]
+struct DownloadRegistry {
+    children: Mutex<HashMap<String, Child>>,
+    /// Downloads killed by `pause_download` whose `download_media` call hasn't
+    /// wound down yet.
+    pausing: Mutex<HashSet<String>>,
+    /// Requests of paused downloads, to be re-run by `resume_download`.
+    paused: Mutex<HashMap<String, DownloadRequest>>,
+    /// Directories running downloads write into, so their temp files are
+    /// left alone by `cleanup_temp_files`.
+    directories: Mutex<HashMap<String, PathBuf>>,
+    /// Downloads waiting for a slot. Removing one cancels it once it gets its turn.
+    queued: Mutex<HashSet<String>>,
+    slots: DownloadSlots,
+}
+
+const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// Limits how many yt-dlp downloads run at once.
+struct DownloadSlots {
+    semaphore: Arc<Semaphore>,
+    /// Current limit, changed with `set_max_concurrent_downloads`.
+    max: Mutex<usize>,
+}
+
+impl Default for DownloadSlots {
+    fn default() -> Self {
+        DownloadSlots {
+            semaphore: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS)),
+            max: Mutex::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS),
+        }
+    }
+}
+
+/// Changes how many downloads may run at once. Lowering the limit doesn't
+/// stop running downloads; new ones wait until enough of them finish.
+#[tauri::command]
+async fn set_max_concurrent_downloads(
+    downloads: State<'_, DownloadRegistry>,
+    max: usize,
+) -> Result<(), AppError> {
+    if max == 0 {
+        return Err(AppError::InvalidInput(
+            "Maximum concurrent downloads must be at least 1".to_string(),
+        ));
+    }
+
+    let mut current = downloads.slots.max.lock().await;
+    println!(
+        "Changing concurrent download limit from {} to {}",
+        current, max
+    );
+    if max > *current {
+        downloads.slots.semaphore.add_permits(max - *current);
+    } else if max < *current {
+        // Take the surplus permits out of circulation as downloads release them
+        let semaphore = downloads.slots.semaphore.clone();
+        let surplus = (*current - max) as u32;
+        tokio::spawn(async move {
+            if let Ok(permits) = semaphore.acquire_many_owned(surplus).await {
+                permits.forget();
+            }
+        });
+    }
+    *current = max;
+    Ok(())
+}
+
+/// Directory yt-dlp writes into: the `-P` path, or our working directory.
+fn download_dir(download_path: Option<&str>) -> PathBuf {
+    match download_path {
+        Some(path) => PathBuf::from(path),
+        None => std::env::current_dir().unwrap_or_default(),
+    }
+}
+
+/// Whether a file name looks like one of yt-dlp's partial or temp files.
+fn is_temp_file(name: &str) -> bool {
+    name.ends_with(".part")
+        || name.ends_with(".ytdl")
+        || name.ends_with(".temp")
+        || name.contains(".part-Frag")
+}
+
+/// Deletes partial and temp files left in `directory` by cancelled or crashed
+/// downloads and returns their paths. Refuses to touch a directory a running
+/// or paused download is using.
+#[tauri::command]
+async fn cleanup_temp_files(
+    downloads: State<'_, DownloadRegistry>,
+    directory: String,
+) -> Result<Vec<String>, AppError> {
+    let dir = std::fs::canonicalize(&directory)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid directory {}: {}", directory, e)))?;
+
+    let running = downloads
+        .directories
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let paused = downloads
+        .paused
+        .lock()
+        .await
+        .values()
+        .map(|request| download_dir(request.download_path.as_deref()))
+        .collect::<Vec<_>>();
+    let in_use = running
+        .iter()
+        .chain(&paused)
+        .any(|used| std::fs::canonicalize(used).is_ok_and(|used| used == dir));
+    if in_use {
+        return Err(AppError::InvalidInput(format!(
+            "A download is still using {}",
+            directory
+        )));
+    }
+
+    let entries = std::fs::read_dir(&dir)
+        .map_err(|e| AppError::Io(format!("Failed to read {}: {}", directory, e)))?;
+    let mut removed = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        let is_temp = path
+            .file_name()
+            .is_some_and(|name| is_temp_file(&name.to_string_lossy()));
+        if !is_temp || !path.is_file() {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed.push(path.to_string_lossy().into_owned()),
+            Err(e) => println!("Failed to remove {:?}: {}", path, e),
+        }
+    }
+
+    println!("Removed {} temp files from {}", removed.len(), directory);
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn cancel_download(
+    downloads: State<'_, DownloadRegistry>,
+    download_id: String,
+) -> Result<(), AppError> {
+    println!("Cancelling download: {}", download_id);
+
+    // Removing the child from the registry tells download_media it was cancelled
+    let child = downloads.children.lock().await.remove(&download_id);
+    match child {
+        Some(mut child) => child
+            .kill()
+            .await
+            .map_err(|e| AppError::Io(format!("Failed to kill yt-dlp: {}", e))),
+        // A paused download has no process left, so just forget it
+        None if downloads.paused.lock().await.remove(&download_id).is_some() => Ok(()),
+        // download_media notices once the download gets a slot
+        None if downloads.queued.lock().await.remove(&download_id) => Ok(()),
+        None => Err(AppError::InvalidInput(format!(
+            "No active download with id: {}",
+            download_id
+        ))),
+    }
+}
+
+/// Stops a download but keeps its `.part` files, so `resume_download` can
+/// continue where it left off.
+#[tauri::command]
+async fn pause_download(
+    downloads: State<'_, DownloadRegistry>,
+    download_id: String,
+) -> Result<(), AppError> {
+    println!("Pausing download: {}", download_id);
+
+    // Marked before taking the child so download_media always sees one or the other
+    downloads.pausing.lock().await.insert(download_id.clone());
+    let child = downloads.children.lock().await.remove(&download_id);
+    let Some(mut child) = child else {
+        // Already finished or cancelled, so nothing will clear the marker
+        downloads.pausing.lock().await.remove(&download_id);
+        return Err(AppError::InvalidInput(format!(
+            "No active download with id: {}",
+            download_id
+        )));
+    };
+
+    child
+        .kill()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to kill yt-dlp: {}", e)))
+}
+
+/// Restarts a paused download with its original request. yt-dlp picks up the
+/// `.part` files thanks to `--continue`.
+#[tauri::command]
+async fn resume_download(
+    window: Window,
+    downloads: State<'_, DownloadRegistry>,
+    history: State<'_, HistoryStore>,
+    settings: State<'_, SettingsStore>,
+    download_id: String,
+) -> Result<String, AppError> {
+    let request = downloads.paused.lock().await.remove(&download_id);
+    let Some(request) = request else {
+        return Err(AppError::InvalidInput(format!(
+            "No paused download with id: {}",
+            download_id
+        )));
+    };
+
+    println!("Resuming download: {}", download_id);
+    // Protect the .part files from cleanup_temp_files while waiting for a slot,
+    // since download_media only registers the directory once yt-dlp starts
+    downloads.directories.lock().await.insert(
+        download_id.clone(),
+        download_dir(request.download_path.as_deref()),
+    );
+    let result = download_media(window, downloads.clone(), history, settings, request).await;
+    downloads.directories.lock().await.remove(&download_id);
+    result
+}
+
+/// Kills every tracked yt-dlp process. Each interrupted `download_media` call
+/// emits its own "cancelled" progress event once its output stream closes.
+#[tauri::command]
+async fn cancel_all_downloads(downloads: State<'_, DownloadRegistry>) -> Result<(), AppError> {
+    downloads.queued.lock().await.clear();
+    let children: Vec<(String, Child)> = downloads.children.lock().await.drain().collect();
+    println!("Cancelling {} active downloads", children.len());
+
+    let mut errors = Vec::new();
+    for (download_id, mut child) in children {
+        // kill() also waits on the process, so nothing is left as a zombie
+        if let Err(e) = child.kill().await {
+            errors.push(format!("{}: {}", download_id, e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Io(format!(
+            "Failed to kill yt-dlp: {}",
+            errors.join(", ")
+        )))
+    }
+}
+
+/// How long a download can go without output before `download-heartbeat`
+/// events are sent.
+const HEARTBEAT_IDLE_SECS: u64 = 10;
+
+/// Sent every second while yt-dlp has been silent for `HEARTBEAT_IDLE_SECS`,
+/// so the UI can tell a quiet download from a hung one.
+#[derive(Clone, Serialize)]
+struct DownloadHeartbeat {
+    download_id: String,
+    idle_seconds: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct PlaylistProgress {
+    download_id: String,
+    current_index: u32,
+    total_items: u32,
+    title: Option<String>,
+}
+
+/// Sent as each playlist item finishes, with the absolute path of its file.
+#[derive(Clone, Serialize)]
+struct PlaylistItemComplete {
+    download_id: String,
+    index: u32,
+    title: Option<String>,
+    path: String,
+}
+
+impl PlaylistItemComplete {
+    fn new(position: &PlaylistProgress, path: &str) -> Self {
+        let path = std::path::absolute(path)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| path.to_string());
+        PlaylistItemComplete {
+            download_id: position.download_id.clone(),
+            index: position.current_index,
+            title: Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            path,
+        }
+    }
+}
+
+/// Sent when a download finishes, with the absolute path of the final file.
+#[derive(Clone, Serialize)]
+struct DownloadComplete {
+    download_id: String,
+    path: String,
+    /// Thumbnails and metadata files yt-dlp reported writing, e.g. for a
+    /// `skip_download` run where `path` is the first of them.
+    sidecar_files: Vec<String>,
+}
+
+/// Parameters for a `download_media` call, sent by the frontend as a single object.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadRequest {
+    /// Identifies the download in events and `cancel_download`. Generated by
+    /// `new_id` when left empty.
+    #[serde(default)]
+    download_id: String,
+    url: String,
+    /// Falls back to the saved `default_format` when empty.
+    #[serde(default)]
+    format: String,
+    /// Falls back to the saved `default_quality` when empty.
+    #[serde(default)]
+    quality: String,
+    download_path: Option<String>,
+    /// Download every item when the URL points at a playlist.
+    #[serde(default)]
+    playlist: bool,
+    /// Which playlist items to download, e.g. `1-5,8,10-`. Requires `playlist`.
+    playlist_items: Option<String>,
+    /// Whether a playlist download moves on when an item fails or stops.
+    /// Defaults to yt-dlp's behavior of moving on.
+    continue_on_error: Option<bool>,
+    /// Target container for audio-only downloads, one of `AUDIO_FORMATS`.
+    audio_format: Option<String>,
+    /// Container to losslessly remux video downloads into, one of `REMUX_CONTAINERS`.
+    remux: Option<String>,
+    /// Codec to re-encode video downloads to, one of `RECODE_CODECS`.
+    recode_video: Option<String>,
+    /// CRF for `recode_video`, lower is better quality. Up to `MAX_CRF`.
+    video_quality: Option<u32>,
+    /// Keep the downloaded video after extracting its audio.
+    #[serde(default)]
+    keep_video: bool,
+    /// Turn the extracted audio into an MP4 showing the cover art as a still
+    /// image, for sites that only accept video uploads. Audio formats only.
+    #[serde(default)]
+    audio_as_video: bool,
+    /// yt-dlp output template replacing `DEFAULT_OUTPUT_TEMPLATE`.
+    output_template: Option<String>,
+    /// Record a livestream from its beginning instead of the current point.
+    #[serde(default)]
+    live_from_start: bool,
+    /// Wait for a scheduled stream or premiere, retrying every this many seconds.
+    wait_for_video: Option<u32>,
+    /// Sort downloads into a subfolder per `uploader`, `playlist` or upload
+    /// `date`. Can't be combined with `output_template`.
+    organize_by: Option<String>,
+    /// Subtitle language codes to download, e.g. `["en", "pt-BR"]`.
+    subtitles: Option<Vec<String>>,
+    /// Also download auto-generated captions for the `subtitles` languages.
+    #[serde(default)]
+    write_auto_subs: bool,
+    /// Subtitle format preference passed to `--sub-format`, e.g. `"srt/vtt/best"`.
+    /// A first choice from `SUBTITLE_CONVERT_FORMATS` is also converted to with
+    /// ffmpeg, since most sites don't offer it directly.
+    sub_format: Option<String>,
+    /// Embed the downloaded subtitles into the media file.
+    #[serde(default)]
+    embed_subs: bool,
+    /// Maximum download rate passed to `--limit-rate`, e.g. `"500K"` or `"2M"`.
+    rate_limit: Option<String>,
+    /// Largest file to download, passed to `--max-filesize`, e.g. `"2G"`.
+    /// Bigger formats are skipped and reported with status `too_large`.
+    max_filesize: Option<String>,
+    /// SponsorBlock categories to cut out, from `SPONSORBLOCK_CATEGORIES`.
+    sponsorblock: Option<Vec<String>>,
+    /// Mark the SponsorBlock segments as chapters instead of removing them.
+    #[serde(default)]
+    sponsorblock_mark: bool,
+    /// Start of the section to download, as `HH:MM:SS` or seconds.
+    start_time: Option<String>,
+    /// End of the section to download, as `HH:MM:SS` or seconds.
+    end_time: Option<String>,
+    /// Download only the first `TEST_SAMPLE_SECONDS` into `sample_dir()`, to
+    /// check a format plays before fetching all of it. The sample's path is
+    /// sent in `download-complete` and it's left out of the history.
+    #[serde(default)]
+    test: bool,
+    /// Embed the thumbnail as cover art, for containers that support it.
+    #[serde(default)]
+    embed_thumbnail: bool,
+    /// Embed title, uploader and other metadata tags.
+    #[serde(default)]
+    embed_metadata: bool,
+    /// Retries for the whole download, defaults to `DEFAULT_RETRIES`.
+    retries: Option<u32>,
+    /// Retries per fragment for DASH/HLS downloads, defaults to `DEFAULT_RETRIES`.
+    fragment_retries: Option<u32>,
+    /// Number of DASH/HLS fragments to fetch in parallel, up to `MAX_CONCURRENT_FRAGMENTS`.
+    concurrent_fragments: Option<u32>,
+    /// External downloader to hand the transfer to. Only `aria2c` is supported.
+    downloader: Option<String>,
+    /// Split the download into one file per chapter.
+    #[serde(default)]
+    split_chapters: bool,
+    /// Embed chapter markers into the file.
+    #[serde(default)]
+    embed_chapters: bool,
+    /// Record downloaded videos in an archive file and skip ones already in it.
+    #[serde(default)]
+    use_archive: bool,
+    /// Archive file for `use_archive`, defaults to `DOWNLOAD_ARCHIVE_FILE` in
+    /// the app data directory.
+    archive_path: Option<String>,
+    /// Highest video resolution to pick when selecting best/worst, e.g. 1080.
+    /// Ignored when `quality` is an explicit format ID.
+    max_height: Option<u32>,
+    /// Only pick formats that already contain both video and audio, so no
+    /// merge is needed. `video+audio` downloads only.
+    #[serde(default)]
+    muxed_only: bool,
+    /// yt-dlp `--format-sort` fields, applied on top of the format selection.
+    /// Common presets:
+    /// - `res,vcodec:av1` prefers AV1 at the highest resolution
+    /// - `vcodec:h264` prefers H.264 for compatibility with older players
+    /// - `ext:mp4:m4a` prefers MP4/M4A containers
+    /// - `res:1080` prefers up to 1080p without ruling out other formats
+    sort: Option<String>,
+    /// Prefer royalty-free formats such as WebM, Vorbis and Opus when the
+    /// best/worst selectors pick between otherwise equal formats.
+    #[serde(default)]
+    prefer_free_formats: bool,
+    /// Trim file names to this many characters, passed to `--trim-filenames`.
+    /// Defaults to `DEFAULT_MAX_FILENAME_LENGTH`.
+    max_filename_length: Option<u32>,
+    /// Strip characters Windows doesn't allow in file names, even on other
+    /// platforms, e.g. for downloads to a shared drive.
+    #[serde(default)]
+    windows_filenames: bool,
+    /// Also send routine `[download]  45.3% ...` progress lines to the log.
+    /// They're left out by default since fragmented downloads print hundreds
+    /// per second; `download-progress` events are sent either way.
+    #[serde(default)]
+    verbose: bool,
+    /// Save yt-dlp's full metadata as a `.info.json` file next to the media.
+    #[serde(default)]
+    write_info_json: bool,
+    /// Also fetch the video's comments into the `.info.json` file, which is
+    /// written even if `write_info_json` is off. Can be slow.
+    #[serde(default)]
+    write_comments: bool,
+    /// Save the video description as a `.description` file next to the media.
+    #[serde(default)]
+    write_description: bool,
+    /// Save the thumbnail as an image file next to the media.
+    #[serde(default)]
+    write_thumbnail: bool,
+    /// Only write the thumbnail and metadata files, without the media itself.
+    #[serde(default)]
+    skip_download: bool,
+    /// What to do when the output file already exists, one of `CONFLICT_POLICIES`.
+    /// yt-dlp's default is to skip the download.
+    on_conflict: Option<String>,
+    /// Check the finished file with `verify_download` and fail the download
+    /// if it's missing, empty or corrupt.
+    #[serde(default)]
+    verify: bool,
+    /// Additional yt-dlp arguments appended after the built-in ones.
+    extra_args: Option<Vec<String>>,
+    #[serde(flatten)]
+    fetch: FetchOptions,
+}
+
+/// Containers accepted by yt-dlp's `--remux-video`.
+const REMUX_CONTAINERS: &[&str] = &["mp4", "mkv", "webm", "mov", "avi", "flv"];
+
+/// Codecs accepted as `recode_video`, with the container the result is saved
+/// in and the ffmpeg video and audio encoders used by `recode_video_file`.
+const RECODE_CODECS: &[(&str, &str, &str, &str)] = &[
+    ("h264", "mp4", "libx264", "aac"),
+    ("h265", "mp4", "libx265", "aac"),
+    ("vp9", "webm", "libvpx-vp9", "libopus"),
+    ("av1", "mkv", "libsvtav1", "copy"),
+];
+
+/// Highest CRF x264 and x265 accept, also used for the other codecs.
+const MAX_CRF: u32 = 51;
+
+/// Subtitle formats yt-dlp's `--convert-subs` can produce.
+const SUBTITLE_CONVERT_FORMATS: &[&str] = &["srt", "vtt", "ass", "lrc"];
+
+/// Accepted `on_conflict` values.
+const CONFLICT_POLICIES: &[&str] = &["overwrite", "skip", "rename"];
+
+/// Finds an output template that doesn't collide with existing files by
+/// numbering the name like "Title (1)". `args` are the download's arguments,
+/// used to ask yt-dlp for the file name it would write. Returns `None` if
+/// the name is free.
+async fn unique_output_template(
+    bin_path: &Path,
+    args: &[String],
+    download_dir: &Path,
+) -> Result<Option<String>, AppError> {
+    let mut print_args: Vec<&str> = args.iter().map(String::as_str).collect();
+    print_args.extend(["--print", "filename"]);
+    let stdout = run_ytdlp(bin_path, &print_args, None).await?;
+    let Some(filename) = stdout.lines().next().map(PathBuf::from) else {
+        return Ok(None);
+    };
+
+    // Keep any subdirectories from the template, relative to the download dir
+    let relative = filename
+        .strip_prefix(download_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| PathBuf::from(filename.file_name().unwrap_or_default()));
+    let parent = relative.parent().unwrap_or(Path::new(""));
+    let stem = relative
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+
+    // Extensions change during post-processing, so compare stems only
+    let taken = |stem: &str| {
+        std::fs::read_dir(download_dir.join(parent))
+            .map(|entries| {
+                entries.filter_map(Result::ok).any(|entry| {
+                    Path::new(&entry.file_name())
+                        .file_stem()
+                        .is_some_and(|existing| existing.to_string_lossy() == stem)
+                })
+            })
+            .unwrap_or(false)
+    };
+    if !taken(&stem) {
+        return Ok(None);
+    }
+
+    let numbered = (1..)
+        .map(|n| format!("{} ({})", stem, n))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_default();
+    // Escape the literal name so yt-dlp doesn't expand it as a template
+    let template = parent
+        .join(format!("{}.%(ext)s", numbered.replace('%', "%%")))
+        .to_string_lossy()
+        .into_owned();
+    Ok(Some(template))
+}
+
+/// Creates an id like `download-<millis>-<n>` for a download or info request
+/// started without one.
+fn new_id(kind: &str) -> String {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    format!(
+        "{}-{}-{}",
+        kind,
+        millis,
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Audio formats accepted by yt-dlp's `--audio-format`.
+const AUDIO_FORMATS: &[&str] = &["mp3", "m4a", "opus", "flac", "wav", "vorbis"];
+
+/// SponsorBlock categories understood by yt-dlp.
+const SPONSORBLOCK_CATEGORIES: &[&str] = &[
+    "sponsor",
+    "intro",
+    "outro",
+    "selfpromo",
+    "preview",
+    "filler",
+    "interaction",
+    "music_offtopic",
+];
+
+/// Parses a timestamp given as seconds (`90`, `90.5`) or `[[HH:]MM:]SS`.
+fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let parts: Vec<&str> = timestamp.trim().split(':').collect();
+    let (last, rest) = parts.split_last()?;
+    if rest.len() > 2 {
+        return None;
+    }
+
+    let seconds: f64 = last.parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 || (!rest.is_empty() && seconds >= 60.0) {
+        return None;
+    }
+
+    let mut minutes = 0u64;
+    for (i, part) in rest.iter().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        // Minutes are capped at 59 when hours are given
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        minutes = minutes * 60 + value;
+    }
+
+    Some(minutes as f64 * 60.0 + seconds)
+}
+
+/// Builds the `--download-sections` value for an optional start/end pair.
+fn download_section(
+    start_time: Option<&str>,
+    end_time: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    let parse = |timestamp: &str| {
+        parse_timestamp(timestamp).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "Invalid timestamp: {} (expected HH:MM:SS or seconds)",
+                timestamp
+            ))
+        })
+    };
+
+    let start = start_time.map(parse).transpose()?;
+    let end = end_time.map(parse).transpose()?;
+
+    match (start, end) {
+        (None, None) => Ok(None),
+        (Some(start), Some(end)) if start >= end => Err(AppError::InvalidInput(format!(
+            "Start time {} must be before end time {}",
+            start_time.unwrap_or_default(),
+            end_time.unwrap_or_default()
+        ))),
+        (start, end) => Ok(Some(format!(
+            "*{}-{}",
+            start.unwrap_or(0.0),
+            end.map_or_else(|| "inf".to_string(), |end| end.to_string())
+        ))),
+    }
+}
+
+/// Length of a `test` download's sample.
+const TEST_SAMPLE_SECONDS: u32 = 10;
+
+/// Temp folder `test` downloads are saved to, away from the user's downloads.
+fn sample_dir() -> PathBuf {
+    std::env::temp_dir().join("frieren-samples")
+}
+
+/// Characters rejected in `extra_args`. yt-dlp isn't run through a shell, but
+/// these almost always mean a shell command was pasted in by mistake.
+/// Parentheses are allowed since output templates use `%(field)s`.
+const SHELL_METACHARACTERS: &[char] = &[';', '&', '|', '$', '`', '<', '>', '\n', '\r'];
+
+const DOWNLOAD_ARCHIVE_FILE: &str = "download-archive.txt";
+
+const DEFAULT_OUTPUT_TEMPLATE: &str = "%(title)s.%(ext)s";
+
+/// Subfolder prepended to `DEFAULT_OUTPUT_TEMPLATE` for an `organize_by`
+/// value, with a fallback for videos missing the field.
+fn organize_folder(organize_by: &str) -> Option<&'static str> {
+    match organize_by {
+        "uploader" => Some("%(uploader|Unknown uploader)s"),
+        "playlist" => Some("%(playlist_title,playlist|No playlist)s"),
+        "date" => Some("%(upload_date>%Y-%m-%d|Unknown date)s"),
+        _ => None,
+    }
+}
+
+/// Names split chapters by number and title, grouped in a folder per video.
+const CHAPTER_OUTPUT_TEMPLATE: &str =
+    "chapter:%(title)s/%(section_number)02d - %(section_title)s.%(ext)s";
+
+/// Retry count used when the frontend doesn't specify one. Higher than
+/// yt-dlp's own default so flaky mobile connections don't abort downloads.
+const DEFAULT_RETRIES: u32 = 10;
+
+const MAX_CONCURRENT_FRAGMENTS: u32 = 16;
+
+/// Rejects output templates that could escape the download directory,
+/// either through `..` segments or by being an absolute path.
+fn validate_output_template(template: &str) -> Result<(), AppError> {
+    if template.trim().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Output template must not be empty".to_string(),
+        ));
+    }
+
+    let escapes = template.starts_with(['/', '\\'])
+        || Path::new(template).has_root()
+        || template.get(1..2) == Some(":")
+        || template.split(['/', '\\']).any(|segment| segment == "..");
+    if escapes {
+        return Err(AppError::InvalidInput(format!(
+            "Output template must stay inside the download directory: {}",
+            template
+        )));
+    }
+
+    Ok(())
+}
+
+/// Self-updates the resolved yt-dlp binary, streaming its output as
+/// `download-log` events. Returns the last line yt-dlp printed.
+#[tauri::command]
+async fn update_ytdlp(window: Window) -> Result<String, AppError> {
+    let bin_path = resolve_ytdlp_path(window.app_handle()).await?;
+    println!("Updating yt-dlp at {:?}", bin_path);
+
+    let mut child = Command::new(&bin_path)
+        .arg("-U")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stdout".to_string()))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stderr".to_string()))?;
+
+    let mut stdout_reader = BufReader::new(stdout).lines();
+    let mut stderr_reader = BufReader::new(stderr).lines();
+
+    let window_clone = window.clone();
+    let stderr_task = tokio::spawn(async move {
+        let mut last_line = None;
+        while let Ok(Some(line)) = stderr_reader.next_line().await {
+            println!("yt-dlp stderr: {}", line);
+            last_line = Some(line.clone());
+            let _ = window_clone.emit(
+                "download-log",
+                LogMessage {
+                    download_id: None,
+                    message_type: stderr_message_type(&line).to_string(),
+                    message: line,
+                },
+            );
+        }
+        last_line
+    });
+
+    let mut last_line = None;
+    while let Ok(Some(line)) = stdout_reader.next_line().await {
+        println!("yt-dlp stdout: {}", line);
+        if !line.trim().is_empty() {
+            last_line = Some(line.trim().to_string());
+        }
+        let _ = window.emit(
+            "download-log",
+            LogMessage {
+                download_id: None,
+                message_type: "stdout".to_string(),
+                message: line,
+            },
+        );
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to wait on child: {}", e)))?;
+    let last_error = stderr_task.await.ok().flatten();
+
+    if status.success() {
+        // A new version may support different sites
+        *window.state::<ExtractorCache>().extractors.lock().await = None;
+        Ok(last_line.unwrap_or_else(|| "yt-dlp is up to date".to_string()))
+    } else {
+        Err(AppError::YtDlpFailed {
+            code: status.code(),
+            stderr: last_error.or(last_line).unwrap_or_default(),
+        })
+    }
+}
+
+/// yt-dlp arguments for a download, plus warnings to show before it starts.
+struct DownloadArgs {
+    args: Vec<String>,
+    warnings: Vec<String>,
+    /// Arguments for a follow-up run writing the requested sidecar files, kept
+    /// separate since yt-dlp skips the media if writing them fails.
+    sidecar_args: Option<Vec<String>>,
+}
+
+/// Arguments selecting which items to fetch and where to save them, shared by
+/// the download, its sidecar run and `get_output_filename`.
+fn location_args(request: &DownloadRequest, organize_folder: Option<&str>) -> Vec<String> {
+    let mut location_args = Vec::new();
+
+    // Only expand playlists when explicitly requested, so a watch URL carrying
+    // a `list=` parameter still downloads just that video
+    if request.playlist {
+        location_args.push("--yes-playlist".to_string());
+        // yt-dlp then counts only the selected items in "Downloading item N of M",
+        // so playlist-progress totals stay correct
+        if let Some(items) = &request.playlist_items {
+            location_args.push("--playlist-items".to_string());
+            location_args.push(items.clone());
+        }
+        match request.continue_on_error {
+            Some(true) => location_args.push("--no-abort-on-error".to_string()),
+            Some(false) => location_args.push("--abort-on-error".to_string()),
+            None => {}
+        }
+    } else {
+        location_args.push("--no-playlist".to_string());
+    }
+
+    // Output template to Downloads folder or current dir
     // Set download path if provided
-    if let Some(path) = download_path {
-        args.push("-P".to_string());
-        args.push(path);
+    if let Some(path) = &request.download_path {
+        location_args.push("-P".to_string());
+        location_args.push(path.clone());
     }
 
     // Output template for filename only
-    args.push("-o".to_string());
-    args.push("%(title)s.%(ext)s".to_string());
+    location_args.push("-o".to_string());
+    // yt-dlp creates the subfolder itself
+    location_args.push(match (&request.output_template, organize_folder) {
+        (Some(template), _) => template.clone(),
+        (None, Some(folder)) => format!("{}/{}", folder, DEFAULT_OUTPUT_TEMPLATE),
+        (None, None) => DEFAULT_OUTPUT_TEMPLATE.to_string(),
+    });
+    if let Some(length) = request.max_filename_length.or(DEFAULT_MAX_FILENAME_LENGTH) {
+        location_args.push("--trim-filenames".to_string());
+        location_args.push(length.to_string());
+    }
+    if request.windows_filenames {
+        location_args.push("--windows-filenames".to_string());
+    }
+    location_args
+}
+
+/// Validates a download request and builds the yt-dlp arguments for it,
+/// locating ffmpeg when the request needs it.
+async fn build_download_args(
+    app: &AppHandle,
+    request: &DownloadRequest,
+) -> Result<DownloadArgs, AppError> {
+    let format = request.format.as_str();
+    let quality = request.quality.as_str();
+    let is_audio = format == "audio" || format == "audio_only";
+
+    if let Some(fragments) = request.concurrent_fragments {
+        if !(1..=MAX_CONCURRENT_FRAGMENTS).contains(&fragments) {
+            return Err(AppError::InvalidInput(format!(
+                "Concurrent fragments must be between 1 and {}, got {}",
+                MAX_CONCURRENT_FRAGMENTS, fragments
+            )));
+        }
+    }
+
+    if let Some(arg) = request
+        .extra_args
+        .iter()
+        .flatten()
+        .find(|arg| arg.contains(SHELL_METACHARACTERS))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Extra argument contains shell metacharacters: {}",
+            arg
+        )));
+    }
+
+    if let Some(items) = &request.playlist_items {
+        if !request.playlist {
+            return Err(AppError::InvalidInput(
+                "Playlist items can only be selected for playlist downloads".to_string(),
+            ));
+        }
+        // Comma-separated indices, "N-M"/"N-" ranges or "start:stop:step" slices
+        let items_regex =
+            Regex::new(r"^\d+(?:-\d*|:\d*(?::\d+)?)?(?:,\d+(?:-\d*|:\d*(?::\d+)?)?)*$")?;
+        if !items_regex.is_match(items) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid playlist items: {} (expected e.g. 1-5,8,10-)",
+                items
+            )));
+        }
+    }
+
+    // Pausing relies on .part files to resume from
+    if let Some(arg) = request
+        .extra_args
+        .iter()
+        .flatten()
+        .find(|arg| *arg == "--no-part" || *arg == "--no-continue")
+    {
+        return Err(AppError::InvalidInput(format!(
+            "{} isn't supported since it breaks resuming downloads",
+            arg
+        )));
+    }
+
+    if let Some(policy) = &request.on_conflict {
+        if !CONFLICT_POLICIES.contains(&policy.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown conflict policy: {} (expected one of {})",
+                policy,
+                CONFLICT_POLICIES.join(", ")
+            )));
+        }
+        if policy == "rename" && request.playlist {
+            return Err(AppError::InvalidInput(
+                "Renaming on conflict is only available for single videos".to_string(),
+            ));
+        }
+    }
+
+    if request.skip_download {
+        if !(request.write_thumbnail
+            || request.write_info_json
+            || request.write_comments
+            || request.write_description)
+        {
+            return Err(AppError::InvalidInput(
+                "Skipping the download requires writing a thumbnail, info JSON or description"
+                    .to_string(),
+            ));
+        }
+        if request.verify || request.audio_as_video {
+            return Err(AppError::InvalidInput(
+                "Verifying or rendering audio as video needs the media, so it can't be combined with skipping the download"
+                    .to_string(),
+            ));
+        }
+    }
+
+    if request.wait_for_video == Some(0) {
+        return Err(AppError::InvalidInput(
+            "Wait for video interval must be greater than 0".to_string(),
+        ));
+    }
+
+    if request.max_filename_length == Some(0) {
+        return Err(AppError::InvalidInput(
+            "Maximum filename length must be greater than 0".to_string(),
+        ));
+    }
+
+    if request.max_height == Some(0) {
+        return Err(AppError::InvalidInput(
+            "Maximum height must be greater than 0".to_string(),
+        ));
+    }
+
+    if let Some(sort) = &request.sort {
+        if sort.trim().is_empty() {
+            return Err(AppError::InvalidInput(
+                "Format sort must not be empty".to_string(),
+            ));
+        }
+    }
+
+    if let Some(rate_limit) = &request.rate_limit {
+        let rate_regex = Regex::new(r"^\d+(\.\d+)?[KkMmGg]?$")?;
+        if !rate_regex.is_match(rate_limit) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid rate limit: {} (expected a number with an optional K, M or G suffix, e.g. 500K or 2M)",
+                rate_limit
+            )));
+        }
+    }
+
+    if let Some(max_filesize) = &request.max_filesize {
+        let size_regex = Regex::new(r"^\d+(\.\d+)?[KkMmGgTt]?$")?;
+        if !size_regex.is_match(max_filesize) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid maximum file size: {} (expected a number with an optional K, M, G or T suffix, e.g. 500M or 2G)",
+                max_filesize
+            )));
+        }
+    }
+
+    if let Some(categories) = &request.sponsorblock {
+        if let Some(unknown) = categories
+            .iter()
+            .find(|category| !SPONSORBLOCK_CATEGORIES.contains(&category.as_str()))
+        {
+            return Err(AppError::InvalidInput(format!(
+                "Unknown SponsorBlock category: {} (expected one of {})",
+                unknown,
+                SPONSORBLOCK_CATEGORIES.join(", ")
+            )));
+        }
+    }
+
+    let wants_subs = request.write_auto_subs
+        || request
+            .subtitles
+            .as_ref()
+            .is_some_and(|langs| !langs.is_empty());
+    if let Some(sub_format) = &request.sub_format {
+        if !wants_subs {
+            return Err(AppError::InvalidInput(
+                "A subtitle format requires subtitles or auto-generated captions".to_string(),
+            ));
+        }
+        let sub_format_regex = Regex::new(r"^[a-z0-9]+(?:/[a-z0-9]+)*$")?;
+        if !sub_format_regex.is_match(sub_format) {
+            return Err(AppError::InvalidInput(format!(
+                "Invalid subtitle format: {} (expected e.g. srt/vtt/best)",
+                sub_format
+            )));
+        }
+    }
+    let convert_subs = request
+        .sub_format
+        .as_deref()
+        .and_then(|sub_format| sub_format.split('/').next())
+        .filter(|first| SUBTITLE_CONVERT_FORMATS.contains(first));
+
+    if request.test {
+        if request.start_time.is_some() || request.end_time.is_some() {
+            return Err(AppError::InvalidInput(
+                "Test downloads can't be combined with a start or end time".to_string(),
+            ));
+        }
+        if request.playlist || request.skip_download {
+            return Err(AppError::InvalidInput(
+                "Test downloads are only available for a single video's media".to_string(),
+            ));
+        }
+        if request.on_conflict.is_some() {
+            return Err(AppError::InvalidInput(
+                "Test downloads can't set a conflict policy, they always replace earlier samples"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let section = if request.test {
+        Some(format!("*0-{}", TEST_SAMPLE_SECONDS))
+    } else {
+        download_section(request.start_time.as_deref(), request.end_time.as_deref())?
+    };
+
+    let fetch_args = request.fetch.to_args()?;
+
+    if let Some(template) = &request.output_template {
+        validate_output_template(template)?;
+    }
+
+    let organize_folder = match &request.organize_by {
+        Some(_) if request.output_template.is_some() => {
+            return Err(AppError::InvalidInput(
+                "Organizing into folders can't be combined with a custom output template"
+                    .to_string(),
+            ));
+        }
+        Some(organize_by) => Some(organize_folder(organize_by).ok_or_else(|| {
+            AppError::InvalidInput(format!(
+                "Unknown organize option: {} (expected uploader, playlist or date)",
+                organize_by
+            ))
+        })?),
+        None => None,
+    };
+
+    if let Some(audio_format) = &request.audio_format {
+        if !AUDIO_FORMATS.contains(&audio_format.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported audio format: {} (expected one of {})",
+                audio_format,
+                AUDIO_FORMATS.join(", ")
+            )));
+        }
+    }
+
+    let mut args = Vec::new();
+    let mut warnings = Vec::new();
+
+    // Merging streams and cutting sections need ffmpeg, so check up front
+    // rather than letting yt-dlp fail after the download
+    let mut ffmpeg_required_for = Vec::new();
+    let is_video_audio = format == "video+audio" || format == "video_audio";
+    if request.muxed_only && !is_video_audio {
+        return Err(AppError::InvalidInput(
+            "Muxed formats can only be selected for video+audio downloads".to_string(),
+        ));
+    }
+    if is_video_audio && !request.muxed_only {
+        ffmpeg_required_for.push("merge video and audio");
+    }
+    if section.is_some() {
+        ffmpeg_required_for.push("download a section of the video");
+    }
+    if let Some(container) = &request.remux {
+        if is_audio {
+            return Err(AppError::InvalidInput(
+                "Remuxing is only available for video downloads".to_string(),
+            ));
+        }
+        if !REMUX_CONTAINERS.contains(&container.as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported remux container: {} (expected one of {})",
+                container,
+                REMUX_CONTAINERS.join(", ")
+            )));
+        }
+        ffmpeg_required_for.push("remux the video");
+    }
+    let recode = match &request.recode_video {
+        Some(codec) => {
+            if is_audio {
+                return Err(AppError::InvalidInput(
+                    "Re-encoding is only available for video downloads".to_string(),
+                ));
+            }
+            if request.remux.is_some() {
+                return Err(AppError::InvalidInput(
+                    "Remuxing and re-encoding can't be combined".to_string(),
+                ));
+            }
+            // Only the final file is re-encoded, and only its video and audio are kept
+            if request.playlist || request.split_chapters {
+                return Err(AppError::InvalidInput(
+                    "Re-encoding is only available for a single video file".to_string(),
+                ));
+            }
+            if request.embed_subs || request.embed_thumbnail {
+                return Err(AppError::InvalidInput(
+                    "Embedded subtitles and thumbnails aren't kept when re-encoding".to_string(),
+                ));
+            }
+            let recode = RECODE_CODECS
+                .iter()
+                .find(|(name, _, _, _)| name == codec)
+                .ok_or_else(|| {
+                    AppError::InvalidInput(format!(
+                        "Unsupported codec: {} (expected one of {})",
+                        codec,
+                        RECODE_CODECS
+                            .iter()
+                            .map(|(name, _, _, _)| *name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })?;
+            ffmpeg_required_for.push("re-encode the video");
+            Some(recode)
+        }
+        None => None,
+    };
+    match request.video_quality {
+        Some(_) if recode.is_none() => {
+            return Err(AppError::InvalidInput(
+                "Video quality requires a codec to re-encode to".to_string(),
+            ));
+        }
+        Some(crf) if crf > MAX_CRF => {
+            return Err(AppError::InvalidInput(format!(
+                "Video quality must be between 0 and {}, got {}",
+                MAX_CRF, crf
+            )));
+        }
+        _ => {}
+    }
+    if request.audio_as_video {
+        if !is_audio {
+            return Err(AppError::InvalidInput(
+                "Audio as video is only available for audio downloads".to_string(),
+            ));
+        }
+        ffmpeg_required_for.push("render audio as video");
+    }
+    if convert_subs.is_some() {
+        ffmpeg_required_for.push("convert subtitles");
+    }
+    if request.split_chapters {
+        ffmpeg_required_for.push("split chapters");
+    } else if request.embed_chapters {
+        ffmpeg_required_for.push("embed chapters");
+    }
+    let has_sponsorblock = request
+        .sponsorblock
+        .as_ref()
+        .is_some_and(|categories| !categories.is_empty());
+    if has_sponsorblock && !request.sponsorblock_mark {
+        ffmpeg_required_for.push("cut out SponsorBlock segments");
+    }
+
+    // Nothing is merged or converted when only sidecar files are written
+    if !ffmpeg_required_for.is_empty() && !request.skip_download {
+        let (ffmpeg_path, ffmpeg_version) = find_ffmpeg().await.map_err(|e| {
+            let err_msg = format!(
+                "ffmpeg is required to {} ({})",
+                ffmpeg_required_for.join(" and "),
+                e
+            );
+            println!("{}", err_msg);
+            AppError::FfmpegNotFound(err_msg)
+        })?;
+        println!("Using {} at {:?}", ffmpeg_version, ffmpeg_path);
+        // yt-dlp only searches PATH itself, so point it at a bundled copy
+        if ffmpeg_path.components().count() > 1 {
+            args.push("--ffmpeg-location".to_string());
+            args.push(ffmpeg_path.to_string_lossy().into_owned());
+        }
+    }
+
+    args.push(request.url.clone());
+    args.extend(fetch_args.iter().cloned());
+    // Resume from .part files left behind by a paused download
+    args.push("--continue".to_string());
+    args.push("--newline".to_string()); // Ensure line-buffered output
+    args.push("--progress".to_string()); // Force progress output
+    args.push("--retries".to_string());
+    args.push(request.retries.unwrap_or(DEFAULT_RETRIES).to_string());
+    args.push("--fragment-retries".to_string());
+    args.push(
+        request
+            .fragment_retries
+            .unwrap_or(DEFAULT_RETRIES)
+            .to_string(),
+    );
+
+    if let Some(fragments) = request.concurrent_fragments {
+        args.push("--concurrent-fragments".to_string());
+        args.push(fragments.to_string());
+    }
+
+    if let Some(downloader) = &request.downloader {
+        if downloader != "aria2c" {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported downloader: {} (expected aria2c)",
+                downloader
+            )));
+        }
+        let (aria2c_path, aria2c_version) = find_binary(ARIA2C_BINARY, "--version")
+            .await
+            .ok_or_else(|| {
+                AppError::DownloaderNotFound(
+                    "aria2c was requested but isn't in the bin directory or PATH".to_string(),
+                )
+            })?;
+        println!("Using {} at {:?}", aria2c_version, aria2c_path);
+        // 16 connections per file, split into 16 parts
+        args.push("--downloader".to_string());
+        args.push(aria2c_path.to_string_lossy().into_owned());
+        args.push("--downloader-args".to_string());
+        args.push("aria2c:-x16 -s16".to_string());
+    }
+
+    // Which items to fetch and where to put them, shared with the sidecar run
+    let location_args = location_args(request, organize_folder);
+    args.extend(location_args.iter().cloned());
 
     // Handle format and quality selection
-    if quality != "best" && quality != "worst" {
+    if request.muxed_only {
+        let height = request
+            .max_height
+            .map(|max_height| format!("[height<={}]", max_height))
+            .unwrap_or_default();
+        args.push("-f".to_string());
+        args.push(match quality {
+            "best" => format!("b[vcodec!=none][acodec!=none]{}", height),
+            "worst" => format!("w[vcodec!=none][acodec!=none]{}", height),
+            // Never append "+ba", which would need a merge
+            _ => quality.to_string(),
+        });
+    } else if quality != "best" && quality != "worst" {
         args.push("-f".to_string());
         // When user selects a specific quality with video+audio format,
         // combine that video format with best audio
         if format == "video+audio" || format == "video_audio" {
             args.push(format!("{}+ba/b", quality)); // selected video + best audio, fallback to best
-        } else if format == "audio" || format == "audio_only" {
-            args.push("ba".to_string()); // Just best audio
+        } else if is_audio {
+            // The selected audio format, or the best audio if the id is for a
+            // video format left over from switching to audio
+            args.push(format!("{}[vcodec=none]/ba", quality));
         } else {
-            args.push(quality.clone()); // video only - use the format as-is
+            args.push(quality.to_string()); // video only - use the format as-is
         }
     } else {
-        match (format.as_str(), quality.as_str()) {
+        // e.g. "[height<=1080]", applied to every video selector below
+        let height = request
+            .max_height
+            .map(|max_height| format!("[height<={}]", max_height))
+            .unwrap_or_default();
+        match (format, quality) {
             ("video_audio", "best") | ("video+audio", "best") => {
                 args.push("-f".to_string());
-                args.push("bv+ba/b".to_string());
+                args.push(format!("bv{0}+ba/b{0}", height));
             }
             ("video_audio", "worst") | ("video+audio", "worst") => {
                 args.push("-f".to_string());
-                args.push("wv+wa/w".to_string());
+                args.push(format!("wv{0}+wa/w{0}", height));
             }
             ("video_only", "best") | ("video", "best") => {
                 args.push("-f".to_string());
-                args.push("bv".to_string());
+                args.push(format!("bv{}", height));
             }
             ("video_only", "worst") | ("video", "worst") => {
                 args.push("-f".to_string());
-                args.push("wv".to_string());
+                args.push(format!("wv{}", height));
             }
             ("audio_only", "best") | ("audio", "best") => {
                 args.push("-x".to_string());
@@ -248,71 +3075,784 @@ async fn download_media(
             }
             _ => {
                 args.push("-f".to_string());
-                args.push("bv+ba/b".to_string());
+                args.push(format!("bv{0}+ba/b{0}", height));
+            }
+        }
+    }
+
+    if let Some(sort) = &request.sort {
+        args.push("-S".to_string());
+        args.push(sort.trim().to_string());
+    }
+
+    // Only changes yt-dlp's sort order, so the selectors above still apply
+    if request.prefer_free_formats {
+        args.push("--prefer-free-formats".to_string());
+    }
+
+    // Convert extracted audio to the requested container
+    if is_audio {
+        if let Some(audio_format) = &request.audio_format {
+            if !args.iter().any(|arg| arg == "-x") {
+                args.push("-x".to_string());
+            }
+            args.push("--audio-format".to_string());
+            args.push(audio_format.clone());
+        }
+    }
+
+    if let Some(container) = &request.remux {
+        args.push("--remux-video".to_string());
+        args.push(container.clone());
+    }
+
+    if request.keep_video {
+        args.push("--keep-video".to_string());
+    }
+
+    // The cover art for render_audio_as_video, saved next to the audio
+    if request.audio_as_video {
+        args.push("--write-thumbnail".to_string());
+        args.push("--convert-thumbnails".to_string());
+        args.push("jpg".to_string());
+    }
+
+    if request.write_thumbnail {
+        args.push("--write-thumbnail".to_string());
+    }
+
+    if let Some(rate_limit) = &request.rate_limit {
+        args.push("--limit-rate".to_string());
+        args.push(rate_limit.clone());
+    }
+
+    if let Some(max_filesize) = &request.max_filesize {
+        args.push("--max-filesize".to_string());
+        args.push(max_filesize.clone());
+    }
+
+    if let Some(section) = section {
+        args.push("--download-sections".to_string());
+        args.push(section);
+    }
+
+    if request.embed_thumbnail {
+        // Only audio conversions let us know the final container up front
+        let container = request
+            .audio_format
+            .as_deref()
+            .filter(|_| is_audio)
+            .map(|audio_format| {
+                if audio_format == "vorbis" {
+                    "ogg"
+                } else {
+                    audio_format
+                }
+            });
+        match container {
+            Some(container) if !THUMBNAIL_CONTAINERS.contains(&container) => {
+                warnings.push(format!(
+                    "WARNING: Thumbnails can't be embedded into {} files, skipping",
+                    container
+                ));
+            }
+            _ => args.push("--embed-thumbnail".to_string()),
+        }
+    }
+
+    if request.use_archive {
+        let archive_path = match &request.archive_path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let dir = app.path().app_data_dir().map_err(|e| {
+                    AppError::Io(format!("Failed to resolve app data directory: {}", e))
+                })?;
+                // yt-dlp creates the archive file but not its directory
+                std::fs::create_dir_all(&dir).map_err(|e| {
+                    AppError::Io(format!("Failed to create app data directory: {}", e))
+                })?;
+                dir.join(DOWNLOAD_ARCHIVE_FILE)
+            }
+        };
+        args.push("--download-archive".to_string());
+        args.push(archive_path.to_string_lossy().into_owned());
+    }
+
+    if request.embed_metadata {
+        args.push("--embed-metadata".to_string());
+    }
+
+    if request.embed_chapters {
+        args.push("--embed-chapters".to_string());
+    }
+
+    if request.live_from_start {
+        args.push("--live-from-start".to_string());
+    }
+    if let Some(seconds) = request.wait_for_video {
+        args.push("--wait-for-video".to_string());
+        args.push(seconds.to_string());
+    }
+
+    if request.split_chapters {
+        args.push("--split-chapters".to_string());
+        // Keep the chapter files of each video together in their own folder
+        args.push("-o".to_string());
+        args.push(CHAPTER_OUTPUT_TEMPLATE.to_string());
+    }
+
+    if let Some(categories) = request
+        .sponsorblock
+        .as_ref()
+        .filter(|categories| !categories.is_empty())
+    {
+        args.push(if request.sponsorblock_mark {
+            "--sponsorblock-mark".to_string()
+        } else {
+            "--sponsorblock-remove".to_string()
+        });
+        args.push(categories.join(","));
+    }
+
+    if wants_subs {
+        let langs = request.subtitles.as_ref().filter(|langs| !langs.is_empty());
+        if langs.is_some() {
+            args.push("--write-subs".to_string());
+        }
+        if request.write_auto_subs {
+            args.push("--write-auto-subs".to_string());
+        }
+        if let Some(langs) = langs {
+            args.push("--sub-langs".to_string());
+            args.push(langs.join(","));
+        }
+        if let Some(sub_format) = &request.sub_format {
+            args.push("--sub-format".to_string());
+            args.push(sub_format.clone());
+        }
+        if let Some(convert_subs) = convert_subs {
+            args.push("--convert-subs".to_string());
+            args.push(convert_subs.to_string());
+        }
+        if request.embed_subs {
+            args.push("--embed-subs".to_string());
+        }
+    }
+
+    // Samples of different formats share a name, so never reuse an old one
+    if request.test {
+        args.push("--force-overwrites".to_string());
+    }
+    match request.on_conflict.as_deref() {
+        Some("overwrite") => args.push("--force-overwrites".to_string()),
+        Some("skip") => args.push("--no-overwrites".to_string()),
+        // "rename" needs yt-dlp to resolve the file name, so download_media
+        // picks the free name right before starting
+        _ => {}
+    }
+
+    if let Some(extra_args) = &request.extra_args {
+        args.extend(extra_args.iter().cloned());
+    }
+
+    // Without any media to lose, sidecar files can be written in the main run
+    // Comments are stored in the info JSON, so they need it written too
+    let mut metadata_args = Vec::new();
+    if request.write_info_json || request.write_comments {
+        metadata_args.push("--write-info-json".to_string());
+    }
+    if request.write_comments {
+        metadata_args.push("--write-comments".to_string());
+    }
+    if request.write_description {
+        metadata_args.push("--write-description".to_string());
+    }
+    if request.skip_download {
+        args.push("--skip-download".to_string());
+        args.extend(metadata_args.iter().cloned());
+    }
+    let sidecar_args = (!request.skip_download && !metadata_args.is_empty()).then(|| {
+        let mut sidecar_args = vec![request.url.clone(), "--skip-download".to_string()];
+        sidecar_args.extend(fetch_args);
+        sidecar_args.extend(location_args);
+        sidecar_args.extend(metadata_args);
+        sidecar_args
+    });
+
+    Ok(DownloadArgs {
+        args,
+        warnings,
+        sidecar_args,
+    })
+}
+
+/// Fills in an empty format or quality from the saved defaults, or from the
+/// frontend's own defaults if none are saved.
+fn fill_default_format(request: &mut DownloadRequest, saved: &Settings) {
+    if request.format.is_empty() {
+        request.format = saved
+            .default_format
+            .clone()
+            .unwrap_or_else(|| "video+audio".to_string());
+    }
+    if request.quality.is_empty() {
+        request.quality = saved
+            .default_quality
+            .clone()
+            .unwrap_or_else(|| "best".to_string());
+    }
+}
+
+/// Returns the full yt-dlp command line a download would run, without
+/// starting it. The first element is the yt-dlp binary. With `on_conflict:
+/// "rename"` the numbered file name is only chosen once the download starts.
+#[tauri::command]
+async fn build_download_command(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    mut request: DownloadRequest,
+) -> Result<Vec<String>, AppError> {
+    request.url = validate_url(&request.url)?;
+    let saved = settings::load(&app, &settings).await?;
+    fill_default_format(&mut request, &saved);
+    if request.download_path.is_none() {
+        request.download_path = saved.last_download_path;
+    }
+    if request.fetch.proxy.is_none() {
+        request.fetch.proxy = saved.proxy;
+    }
+    let bin_path = resolve_ytdlp_path(&app).await?;
+    let DownloadArgs { args, warnings, .. } = build_download_args(&app, &request).await?;
+    for warning in warnings {
+        println!("{}", warning);
+    }
+
+    let mut command = vec![bin_path.to_string_lossy().into_owned()];
+    command.extend(args);
+    Ok(command)
+}
+
+#[tauri::command]
+async fn download_media(
+    window: Window,
+    downloads: State<'_, DownloadRegistry>,
+    history: State<'_, HistoryStore>,
+    settings: State<'_, SettingsStore>,
+    mut request: DownloadRequest,
+) -> Result<String, AppError> {
+    request.url = validate_url(&request.url)?;
+    if request.download_id.is_empty() {
+        request.download_id = new_id("download");
+    }
+
+    let saved = settings::load(window.app_handle(), &settings)
+        .await
+        .unwrap_or_else(|e| {
+            println!("Failed to load settings: {}", e);
+            Settings::default()
+        });
+    fill_default_format(&mut request, &saved);
+    if request.fetch.proxy.is_none() {
+        request.fetch.proxy = saved.proxy;
+    }
+
+    // Fall back to the last-used directory, and remember a newly picked one
+    let remember_path = match &request.download_path {
+        _ if request.test => {
+            request.download_path = Some(sample_dir().to_string_lossy().into_owned());
+            None
+        }
+        Some(path) => Some(path.clone()),
+        None => {
+            request.download_path = saved.last_download_path;
+            None
+        }
+    };
+
+    let DownloadArgs {
+        mut args,
+        warnings,
+        sidecar_args,
+    } = build_download_args(window.app_handle(), &request).await?;
+    // Kept in case the download is paused and resumed later
+    let resume_request = request.clone();
+    if let Some(path) = remember_path {
+        if let Err(e) =
+            settings::remember_download_path(window.app_handle(), &settings, &path).await
+        {
+            println!("Failed to save download path: {}", e);
+        }
+    }
+    let DownloadRequest {
+        download_id,
+        url,
+        format,
+        quality,
+        download_path,
+        playlist,
+        verbose,
+        audio_as_video,
+        on_conflict,
+        verify,
+        fetch,
+        test,
+        embed_thumbnail,
+        recode_video,
+        video_quality,
+        ..
+    } = request;
+    let proxy = fetch.proxy;
+
+    println!(
+        "Downloading [{}]: {} (Format: {}, Quality: {}, Path: {:?})",
+        download_id, url, format, quality, download_path
+    );
+
+    // Wait for a free slot, held until this function returns
+    let _permit = match downloads.slots.semaphore.try_acquire() {
+        Ok(permit) => permit,
+        Err(_) => {
+            println!("Download {} queued", download_id);
+            downloads.queued.lock().await.insert(download_id.clone());
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    status: "queued".to_string(),
+                    ..Default::default()
+                },
+            );
+            let permit = downloads
+                .slots
+                .semaphore
+                .acquire()
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            if !downloads.queued.lock().await.remove(&download_id) {
+                println!("Queued download {} was cancelled", download_id);
+                let _ = window.emit(
+                    "download-progress",
+                    DownloadProgress {
+                        download_id: download_id.clone(),
+                        status: "cancelled".to_string(),
+                        ..Default::default()
+                    },
+                );
+                return Err(AppError::Cancelled);
             }
+            permit
+        }
+    };
+
+    // Emit initial log to frontend
+    let _ = window.emit(
+        "download-log",
+        LogMessage {
+            download_id: Some(download_id.clone()),
+            message_type: "stdout".to_string(),
+            message: format!(
+                "Starting download... URL: {}, Path: {:?}",
+                url, download_path
+            ),
+        },
+    );
+    for warning in warnings {
+        let _ = window.emit(
+            "download-log",
+            LogMessage {
+                download_id: Some(download_id.clone()),
+                message_type: "warning".to_string(),
+                message: warning,
+            },
+        );
+    }
+
+    let bin_path = resolve_ytdlp_path(window.app_handle())
+        .await
+        .inspect_err(|err_msg| {
+            println!("{}", err_msg);
+        })?;
+    println!("yt-dlp found at {:?}", bin_path);
+
+    if on_conflict.as_deref() == Some("rename") {
+        let download_dir = download_dir(download_path.as_deref());
+        // A later -o takes precedence over the one from build_download_args
+        if let Some(template) = unique_output_template(&bin_path, &args, &download_dir).await? {
+            args.push("-o".to_string());
+            args.push(template);
         }
     }
 
     println!("Spawning yt-dlp with args: {:?}", args);
 
     // Create a new command
-    let mut child = Command::new(bin_path)
+    let mut child = Command::new(&bin_path)
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped()) // Capture stderr
         .spawn()
         .map_err(|e| {
-            let err_msg = format!("Failed to spawn yt-dlp: {}", e);
-            println!("{}", err_msg);
-            err_msg
+            println!("Failed to spawn yt-dlp: {}", e);
+            AppError::SpawnFailed(e.to_string())
         })?;
 
     println!("yt-dlp spawned successfully, reading output...");
 
-    let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stdout".to_string()))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stderr".to_string()))?;
 
     let mut stdout_reader = BufReader::new(stdout).lines();
     let mut stderr_reader = BufReader::new(stderr).lines();
 
-    // Regex to capture progress percentage
-    let progress_regex = Regex::new(r"(\d+\.?\d*)%").map_err(|e| e.to_string())?;
+    // Regexes to capture progress percentage, total size, speed and ETA
+    // e.g. "[download]  45.3% of ~ 10.50MiB at    2.50MiB/s ETA 00:42"
+    // Anchored to the progress line so a "50%" in a title or URL isn't picked up
+    let progress_regex = Regex::new(r"^\[download\]\s+(\d+(?:\.\d+)?)%\s+of\s")?;
+    let size_regex = Regex::new(r"of\s+~?\s*(\d+\.?\d*\s*[KMGT]?i?B)")?;
+    let speed_regex = Regex::new(r"at\s+(\d+\.?\d*\s*[KMGT]?i?B/s)")?;
+    let eta_regex = Regex::new(r"ETA\s+(\d+(?::\d+)+)")?;
+    // Livestreams have no known total, e.g. "[download]   12.34MiB at  1.23MiB/s (00:00:10)"
+    let live_progress_regex = Regex::new(r"^\[download\]\s+(\d+\.?\d*\s*[KMGT]?i?B)\s+at\s")?;
+    // e.g. "[download]  12.3% of ~ 1.23GiB at 5.00MiB/s ETA 03:12 (frag 45/366)"
+    let fragment_regex = Regex::new(r"\(frag (\d+)/(\d+)\)")?;
+    // e.g. "[download] Downloading item 3 of 12"
+    let playlist_item_regex =
+        Regex::new(r"^\[download\] Downloading (?:item|video) (\d+) of (\d+)")?;
+    // e.g. "[Merger] Merging formats into ..." or "[FixupM4a] Correcting container ..."
+    let postprocessor_regex = Regex::new(r"^\[(\w+)\]")?;
+    let destination_regex = Regex::new(r"^\[download\] Destination: (.+)$")?;
+    // e.g. "[info] dQw4w9WgXcQ: Downloading 1 format(s): 137+140"
+    let formats_regex = Regex::new(r"Downloading \d+ format\(s\): (\S+)")?;
+    // Streams of a merged format are written as "<title>.f<format_id>.<ext>"
+    let stream_file_regex = Regex::new(r"\.f([\w-]+)\.\w+$")?;
+    // Lines naming the final file once post-processing produced it
+    let final_file_regexes = [
+        Regex::new(r#"^\[Merger\] Merging formats into "(.+)"$"#)?,
+        Regex::new(r"^\[ExtractAudio\] Destination: (.+)$")?,
+        // e.g. "[VideoRemuxer] Remuxing video from webm to mp4; Destination: <title>.mp4"
+        Regex::new(r"^\[VideoRemuxer\] .*; Destination: (.+)$")?,
+        // e.g. "[VideoConvertor] Converting video from webm to mp4; Destination: <title>.mp4"
+        Regex::new(r"^\[VideoConvertor\] .*; Destination: (.+)$")?,
+    ];
+    let already_downloaded_regex = Regex::new(r"^\[download\] (.+) has already been downloaded")?;
+    // e.g. "[download] File is larger than max-filesize (3221225472 bytes > 2147483648 bytes). Aborting."
+    let too_large_regex = Regex::new(
+        r"^\[download\] File is larger than max-filesize \((\d+) bytes > (\d+) bytes\)",
+    )?;
+    // e.g. "[info] Writing video metadata as JSON to: <title>.info.json"
+    let sidecar_file_regex = Regex::new(r"^\[info\] Writing .+ to: (.+)$")?;
+
+    // Register the child so cancel_download can find it
+    downloads
+        .children
+        .lock()
+        .await
+        .insert(download_id.clone(), child);
+    downloads
+        .directories
+        .lock()
+        .await
+        .insert(download_id.clone(), download_dir(download_path.as_deref()));
+
+    // When yt-dlp last printed anything, in milliseconds since `spawned`
+    let spawned = Instant::now();
+    let last_output = Arc::new(AtomicU64::new(0));
 
     // Spawn a task to read stderr concurrently so it doesn't block
     let window_clone = window.clone();
-    tokio::spawn(async move {
+    let stderr_download_id = download_id.clone();
+    let stderr_last_output = last_output.clone();
+    let stderr_task = tokio::spawn(async move {
+        let mut summary = StderrSummary::default();
         while let Ok(Some(line)) = stderr_reader.next_line().await {
             println!("yt-dlp stderr: {}", line);
+            stderr_last_output.store(spawned.elapsed().as_millis() as u64, Ordering::Relaxed);
+            summary.push_tail(&line);
+            summary.proxy_failed |= is_proxy_error(&line);
+            if embed_thumbnail && is_thumbnail_embed_error(&line) {
+                summary.thumbnail_embed_failed = true;
+            } else if line.starts_with("ERROR:") {
+                summary.errors.push(line.clone());
+            }
             let _ = window_clone.emit(
                 "download-log",
                 LogMessage {
-                    message_type: "stderr".to_string(),
+                    download_id: Some(stderr_download_id.clone()),
+                    message_type: stderr_message_type(&line).to_string(),
                     message: line,
                 },
             );
         }
+        summary
     });
 
+    // Current playlist position, with the title filled in once the item's destination is known
+    let mut playlist_position: Option<PlaylistProgress> = None;
+    // Most recent file yt-dlp reported writing to
+    let mut destination: Option<String> = None;
+    let mut sidecar_files: Vec<String> = Vec::new();
+    // Final file of the current playlist item, once it has finished downloading
+    let mut item_file: Option<String> = None;
+    // Whether the EmbedThumbnail post-processor started, so an error can be blamed on it
+    let mut embedding_thumbnail = false;
+    // Format IDs making up the current item's selection, e.g. ["137", "140"],
+    // and which of them is being downloaded
+    let mut stream_formats: Vec<String> = Vec::new();
+    let mut stream_index: Option<usize> = None;
+
+    // Description, phase and start of the running post-processing step
+    let mut processing: Option<(&str, Option<String>, Instant)> = None;
+    let mut heartbeat = tokio::time::interval(Duration::from_secs(1));
+
     println!("Starting to read stdout...");
-    while let Ok(Some(line)) = stdout_reader.next_line().await {
+    loop {
+        let line = tokio::select! {
+            line = stdout_reader.next_line() => match line {
+                Ok(Some(line)) => line,
+                _ => break,
+            },
+            _ = heartbeat.tick() => {
+                if let Some((description, phase, started)) = &processing {
+                    let _ = window.emit(
+                        "download-progress",
+                        DownloadProgress {
+                            download_id: download_id.clone(),
+                            progress: 100.0,
+                            status: "processing".to_string(),
+                            detail: Some(description.to_string()),
+                            phase: phase.clone(),
+                            elapsed_seconds: Some(started.elapsed().as_secs()),
+                            ..Default::default()
+                        },
+                    );
+                }
+                let idle = spawned
+                    .elapsed()
+                    .saturating_sub(Duration::from_millis(last_output.load(Ordering::Relaxed)));
+                if idle >= Duration::from_secs(HEARTBEAT_IDLE_SECS) {
+                    let _ = window.emit(
+                        "download-heartbeat",
+                        DownloadHeartbeat {
+                            download_id: download_id.clone(),
+                            idle_seconds: idle.as_secs(),
+                        },
+                    );
+                }
+                continue;
+            }
+        };
         println!("yt-dlp stdout: {}", line); // Log output for debugging
+        last_output.store(spawned.elapsed().as_millis() as u64, Ordering::Relaxed);
 
-        let _ = window.emit(
-            "download-log",
-            LogMessage {
-                message_type: "stdout".to_string(),
-                message: line.clone(),
-            },
-        );
+        // Any output ends the previous step; the next one reports itself below
+        processing = None;
+
+        if verbose || !progress_regex.is_match(&line) {
+            let _ = window.emit(
+                "download-log",
+                LogMessage {
+                    download_id: Some(download_id.clone()),
+                    message_type: "stdout".to_string(),
+                    message: line.clone(),
+                },
+            );
+        }
+
+        if let Some(caps) = std::iter::once(&destination_regex)
+            .chain(&final_file_regexes)
+            .find_map(|regex| regex.captures(&line))
+        {
+            destination = Some(caps[1].to_string());
+        }
+        if let Some(caps) = final_file_regexes
+            .iter()
+            .find_map(|regex| regex.captures(&line))
+        {
+            item_file = Some(caps[1].to_string());
+        }
+        embedding_thumbnail |= line.starts_with("[EmbedThumbnail]");
+        if let Some(caps) = sidecar_file_regex.captures(&line) {
+            sidecar_files.push(caps[1].to_string());
+        }
+
+        // yt-dlp skips existing files without printing any progress, so
+        // report it explicitly rather than leaving the UI waiting
+        if let Some(caps) = already_downloaded_regex.captures(&line) {
+            destination = Some(caps[1].to_string());
+            item_file = destination.clone();
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    progress: 100.0,
+                    status: if on_conflict.as_deref() == Some("skip") {
+                        "skipped".to_string()
+                    } else {
+                        "already_downloaded".to_string()
+                    },
+                    ..Default::default()
+                },
+            );
+            continue;
+        }
+
+        if let Some(caps) = too_large_regex.captures(&line) {
+            let total_bytes = caps[1].parse::<u64>().ok();
+            let limit = caps[2].parse::<u64>().ok();
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    status: "too_large".to_string(),
+                    total_bytes,
+                    detail: total_bytes.zip(limit).map(|(size, limit)| {
+                        format!(
+                            "Skipped: the file is {}, over the {} limit",
+                            format_size(size),
+                            format_size(limit)
+                        )
+                    }),
+                    ..Default::default()
+                },
+            );
+            continue;
+        }
+
+        if let Some(caps) = formats_regex.captures(&line) {
+            stream_formats = caps[1].split('+').map(str::to_string).collect();
+            stream_index = None;
+        } else if let Some(caps) = destination_regex.captures(&line) {
+            if let Some(file_caps) = stream_file_regex.captures(&caps[1]) {
+                stream_index = stream_formats.iter().position(|id| id == &file_caps[1]);
+            }
+        }
+        // Only merged selections have per-stream phases; the video stream comes first
+        let stream = stream_index.filter(|_| stream_formats.len() > 1);
+        let phase = stream.map(|index| if index == 0 { "video" } else { "audio" });
+
+        if playlist {
+            if let Some(caps) = playlist_item_regex.captures(&line) {
+                // The previous item is done once yt-dlp moves on to the next
+                if let (Some(position), Some(path)) = (&playlist_position, item_file.take()) {
+                    let _ = window.emit(
+                        "playlist-item-complete",
+                        PlaylistItemComplete::new(position, &path),
+                    );
+                }
+                let position = PlaylistProgress {
+                    download_id: download_id.clone(),
+                    current_index: caps[1].parse().unwrap_or(0),
+                    total_items: caps[2].parse().unwrap_or(0),
+                    title: None,
+                };
+                let _ = window.emit("playlist-progress", position.clone());
+                playlist_position = Some(position);
+            } else if let Some(caps) = destination_regex.captures(&line) {
+                if let Some(position) = playlist_position.as_mut() {
+                    // Drop a stream file's ".f137.mp4" so the title matches the merged file
+                    let path = Path::new(&caps[1]);
+                    let title = match stream_file_regex.find(&caps[1]) {
+                        Some(suffix) => Path::new(&caps[1][..suffix.start()]).file_name(),
+                        None => path.file_stem(),
+                    };
+                    position.title = title.map(|title| title.to_string_lossy().into_owned());
+                    let _ = window.emit("playlist-progress", position.clone());
+                }
+            }
+        }
+
+        // Post-processing runs after the download hits 100%, so report it as
+        // its own phase instead of leaving the UI sitting at 100%
+        if let Some((name, description)) = postprocessor_regex.captures(&line).and_then(|caps| {
+            let name = caps.get(1)?.as_str();
+            Some((name, postprocessor_description(name)?))
+        }) {
+            let phase = (name == "Merger").then(|| "merge".to_string());
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    progress: 100.0,
+                    status: "processing".to_string(),
+                    detail: Some(description.to_string()),
+                    phase: phase.clone(),
+                    elapsed_seconds: Some(0),
+                    ..Default::default()
+                },
+            );
+            processing = Some((description, phase, Instant::now()));
+            continue;
+        }
+
+        if let Some(caps) = live_progress_regex.captures(&line) {
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    status: "recording".to_string(),
+                    speed: speed_regex.captures(&line).map(|caps| caps[1].to_string()),
+                    downloaded_bytes: parse_size(&caps[1]),
+                    phase: phase.map(str::to_string),
+                    ..Default::default()
+                },
+            );
+            continue;
+        }
 
         if let Some(caps) = progress_regex.captures(&line) {
             if let Some(match_) = caps.get(1) {
-                if let Ok(progress) = match_.as_str().parse::<f64>() {
+                if let Ok(mut progress) = match_.as_str().parse::<f64>() {
+                    // Merging or extracting audio later replaces this with the final file
+                    if progress >= 100.0 {
+                        item_file = destination.clone();
+                    }
+                    // yt-dlp only prints the total, so derive the downloaded bytes from it
+                    let total_size = size_regex.captures(&line).map(|caps| caps[1].to_string());
+                    let total_bytes = total_size.as_deref().and_then(parse_size);
+                    let downloaded_bytes =
+                        total_bytes.map(|total| (total as f64 * progress / 100.0) as u64);
+
+                    // Fragmented downloads report a jumpy byte estimate, so
+                    // derive progress from the fragment count instead
+                    let fragments = fragment_regex.captures(&line).and_then(|caps| {
+                        Some((caps[1].parse::<u32>().ok()?, caps[2].parse::<u32>().ok()?))
+                    });
+                    if let Some((index, count)) = fragments.filter(|&(_, count)| count > 0) {
+                        progress = (f64::from(index) / f64::from(count) * 100.0).min(100.0);
+                    }
+                    // Spread the streams of a merged format over one 0-100% range
+                    if let Some(index) = stream {
+                        progress = (index as f64 * 100.0 + progress) / stream_formats.len() as f64;
+                    }
+
+                    let capture = |regex: &Regex| {
+                        regex
+                            .captures(&line)
+                            .and_then(|caps| caps.get(1))
+                            .map(|m| m.as_str().to_string())
+                    };
                     let _ = window.emit(
                         "download-progress",
                         DownloadProgress {
+                            download_id: download_id.clone(),
                             progress,
                             status: "downloading".to_string(),
+                            speed: capture(&speed_regex),
+                            eta: capture(&eta_regex),
+                            total_size,
+                            downloaded_bytes,
+                            total_bytes,
+                            detail: None,
+                            fragment_index: fragments.map(|(index, _)| index),
+                            fragment_count: fragments.map(|(_, count)| count),
+                            phase: phase.map(str::to_string),
+                            elapsed_seconds: None,
                         },
                     );
                 }
@@ -321,16 +3861,551 @@ async fn download_media(
     }
     println!("Finished reading stdout.");
 
+    downloads.directories.lock().await.remove(&download_id);
+
+    // If the child is no longer registered, cancel_download already killed it
+    let Some(mut child) = downloads.children.lock().await.remove(&download_id) else {
+        if downloads.pausing.lock().await.remove(&download_id) {
+            println!("Download {} was paused", download_id);
+            let _ = window.emit(
+                "download-progress",
+                DownloadProgress {
+                    download_id: download_id.clone(),
+                    status: "paused".to_string(),
+                    ..Default::default()
+                },
+            );
+            downloads
+                .paused
+                .lock()
+                .await
+                .insert(download_id, resume_request);
+            return Err(AppError::Paused);
+        }
+
+        println!("Download {} was cancelled", download_id);
+        let _ = window.emit(
+            "download-progress",
+            DownloadProgress {
+                download_id: download_id.clone(),
+                progress: 0.0,
+                status: "cancelled".to_string(),
+                ..Default::default()
+            },
+        );
+        return Err(AppError::Cancelled);
+    };
+
     let status = child
         .wait()
         .await
-        .map_err(|e| format!("Failed to wait on child: {}", e))?;
+        .map_err(|e| AppError::Io(format!("Failed to wait on child: {}", e)))?;
     println!("yt-dlp exit status: {}", status);
 
-    if status.success() {
+    let stderr_summary = stderr_task.await.unwrap_or_default();
+
+    let mut result = if status.success() {
         Ok("Download successful".to_string())
+    } else if embedding_thumbnail
+        && stderr_summary.thumbnail_embed_failed
+        && stderr_summary.errors.is_empty()
+    {
+        // A failed thumbnail embed still leaves a usable file, so only warn about it
+        let _ = window.emit(
+            "download-log",
+            LogMessage {
+                download_id: Some(download_id.clone()),
+                message_type: "warning".to_string(),
+                message: "WARNING: The thumbnail could not be embedded into this file".to_string(),
+            },
+        );
+        Ok("Download successful (thumbnail could not be embedded)".to_string())
+    } else if let Some(proxy) = proxy.filter(|_| stderr_summary.proxy_failed) {
+        Err(AppError::ProxyFailed {
+            proxy,
+            stderr: stderr_summary.failure_details(status),
+        })
+    } else if let Some(error) = AppError::from_ytdlp_stderr(&stderr_summary.errors.join("\n")) {
+        Err(error)
+    } else {
+        Err(AppError::YtDlpFailed {
+            code: status.code(),
+            stderr: stderr_summary.failure_details(status),
+        })
+    };
+
+    // Sidecar files are best effort, so failing to write them only warns
+    if let (Ok(_), Some(sidecar_args)) = (&result, sidecar_args) {
+        println!("Writing sidecar files with args: {:?}", sidecar_args);
+        let failure =
+            match write_sidecar_files(&window, &download_id, &bin_path, &sidecar_args).await {
+                Ok(()) => None,
+                Err(e) => Some(e.to_string()),
+            };
+        if let Some(failure) = failure {
+            let _ = window.emit(
+                "download-log",
+                LogMessage {
+                    download_id: Some(download_id.clone()),
+                    message_type: "warning".to_string(),
+                    message: format!("WARNING: Could not write metadata files: {}", failure),
+                },
+            );
+        }
+    }
+
+    // Relative paths are resolved against our working directory, like yt-dlp does
+    let absolute = |path: String| {
+        std::path::absolute(&path)
+            .map(|absolute| absolute.to_string_lossy().into_owned())
+            .unwrap_or(path)
+    };
+    let sidecar_files: Vec<String> = sidecar_files.into_iter().map(absolute).collect();
+    // A skip_download run has no media, so point at what it did write
+    let mut destination = destination
+        .map(absolute)
+        .or_else(|| sidecar_files.first().cloned());
+
+    if let (Ok(_), true, Some(audio)) = (&result, audio_as_video, &destination) {
+        match render_audio_as_video(Path::new(audio)).await {
+            Ok(video) => destination = Some(video.to_string_lossy().into_owned()),
+            // The audio itself is fine, so only warn
+            Err(e) => {
+                let _ = window.emit(
+                    "download-log",
+                    LogMessage {
+                        download_id: Some(download_id.clone()),
+                        message_type: "warning".to_string(),
+                        message: format!("WARNING: Could not render audio as video: {}", e),
+                    },
+                );
+            }
+        }
+    }
+    let recode = recode_video
+        .as_deref()
+        .and_then(|codec| RECODE_CODECS.iter().find(|(name, _, _, _)| *name == codec));
+    if let (Ok(_), Some(codec), Some(video)) = (&result, recode, &destination) {
+        let _ = window.emit(
+            "download-progress",
+            DownloadProgress {
+                download_id: download_id.clone(),
+                progress: 100.0,
+                status: "processing".to_string(),
+                detail: Some("Re-encoding video".to_string()),
+                elapsed_seconds: Some(0),
+                ..Default::default()
+            },
+        );
+        match recode_video_file(Path::new(video), codec, video_quality).await {
+            Ok(recoded) => destination = Some(recoded.to_string_lossy().into_owned()),
+            Err(e) => result = Err(e),
+        }
+    }
+    // Playlists only report their last file, which is the one checked here
+    let result = match (result, verify, &destination) {
+        (Ok(message), true, Some(path)) => match verify_download(Path::new(path)).await {
+            Ok(()) => Ok(message),
+            Err(e) => {
+                let _ = window.emit(
+                    "download-progress",
+                    DownloadProgress {
+                        download_id: download_id.clone(),
+                        progress: 100.0,
+                        status: "verify_failed".to_string(),
+                        detail: Some(e.to_string()),
+                        ..Default::default()
+                    },
+                );
+                Err(e)
+            }
+        },
+        (result, _, _) => result,
+    };
+    if let (Ok(_), Some(position), Some(path)) = (&result, &playlist_position, item_file) {
+        let _ = window.emit(
+            "playlist-item-complete",
+            PlaylistItemComplete::new(position, &path),
+        );
+    }
+    if let (Ok(_), Some(path)) = (&result, &destination) {
+        let _ = window.emit(
+            "download-complete",
+            DownloadComplete {
+                download_id: download_id.clone(),
+                path: path.clone(),
+                sidecar_files,
+            },
+        );
+    }
+
+    let title = destination
+        .as_deref()
+        .and_then(|destination| Path::new(destination).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned());
+    // Samples are thrown away, so there's nothing worth keeping in the history
+    if !test {
+        let entry = HistoryEntry::new(
+            url,
+            title,
+            format,
+            destination.or(download_path),
+            result.is_ok(),
+        );
+        if let Err(e) = history::record(window.app_handle(), &history, entry).await {
+            println!("Failed to record download history: {}", e);
+        }
+    }
+
+    result
+}
+
+/// Runs the sidecar pass, forwarding its output to the log since fetching
+/// comments can take a while. Returns yt-dlp's stderr if it fails.
+async fn write_sidecar_files(
+    window: &Window,
+    download_id: &str,
+    bin_path: &Path,
+    sidecar_args: &[String],
+) -> Result<(), AppError> {
+    let mut child = Command::new(bin_path)
+        .args(sidecar_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::SpawnFailed(e.to_string()))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stdout".to_string()))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| AppError::Io("Failed to open stderr".to_string()))?;
+    let stderr_task = tokio::spawn(async move {
+        let mut output = String::new();
+        let _ = stderr.read_to_string(&mut output).await;
+        output
+    });
+
+    // e.g. "[youtube] dQw4w9WgXcQ: Downloading comment API JSON page 3 (40/~1500)"
+    let mut stdout_reader = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = stdout_reader.next_line().await {
+        let _ = window.emit(
+            "download-log",
+            LogMessage {
+                download_id: Some(download_id.to_string()),
+                message_type: "stdout".to_string(),
+                message: line,
+            },
+        );
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to wait on child: {}", e)))?;
+    let stderr_output = stderr_task.await.unwrap_or_default();
+    if status.success() {
+        Ok(())
     } else {
-        Err(format!("Download failed with status: {}", status))
+        Err(AppError::Io(stderr_output.trim().to_string()))
+    }
+}
+
+/// Shows a downloaded file in the system file manager. Explorer and Finder
+/// select the file; on Linux its folder is opened.
+#[tauri::command]
+fn open_download_folder(path: String) -> Result<(), AppError> {
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        return Err(AppError::InvalidInput(format!(
+            "File not found: {}",
+            path.display()
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    let result = {
+        let dir = if path.is_dir() {
+            path.as_path()
+        } else {
+            path.parent().unwrap_or(&path)
+        };
+        tauri_plugin_opener::open_path(dir, None::<&str>)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let result = tauri_plugin_opener::reveal_item_in_dir(&path);
+
+    result.map_err(|e| AppError::Io(format!("Failed to open folder: {}", e)))
+}
+
+/// Opens an app directory in the system file manager, creating it first
+/// since nothing may have been written there yet.
+fn open_app_dir(dir: tauri::Result<PathBuf>) -> Result<(), AppError> {
+    let dir = dir.map_err(|e| AppError::Io(format!("Failed to resolve directory: {}", e)))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    tauri_plugin_opener::open_path(&dir, None::<&str>)
+        .map_err(|e| AppError::Io(format!("Failed to open folder: {}", e)))
+}
+
+/// Opens the directory holding the download history.
+#[tauri::command]
+fn open_app_data_dir(app: AppHandle) -> Result<(), AppError> {
+    open_app_dir(app.path().app_data_dir())
+}
+
+/// Opens the directory holding `settings.json`. Logs only go to stdout, so
+/// there's no log directory to open.
+#[tauri::command]
+fn open_app_config_dir(app: AppHandle) -> Result<(), AppError> {
+    open_app_dir(app.path().app_config_dir())
+}
+
+#[derive(Clone, Serialize)]
+struct BatchProgress {
+    current: usize,
+    total: usize,
+    url: String,
+    /// The id the item's `download-progress` events are sent under.
+    download_id: String,
+}
+
+/// Outcome of a batch download: URLs that finished, `(url, error)` pairs
+/// for those that didn't, and the item that was paused, if any.
+#[derive(Debug, Default, Serialize)]
+struct BatchSummary {
+    succeeded: Vec<String>,
+    failed: Vec<(String, String)>,
+    /// Can still be finished with `resume_download`.
+    paused: Option<String>,
+}
+
+/// Downloads each URL in turn with the same format settings. A failed URL is
+/// logged and skipped, or stops the batch when `continue_on_error` is false.
+/// Cancelling or pausing the current item always stops the batch.
+#[tauri::command]
+async fn download_batch(
+    window: Window,
+    downloads: State<'_, DownloadRegistry>,
+    urls: Vec<String>,
+    format: String,
+    quality: String,
+    download_path: Option<String>,
+    continue_on_error: Option<bool>,
+) -> Result<BatchSummary, AppError> {
+    println!("Starting batch download of {} URLs", urls.len());
+    let continue_on_error = continue_on_error.unwrap_or(true);
+
+    let batch_started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+
+    let total = urls.len();
+    let mut summary = BatchSummary::default();
+    for (index, url) in urls.into_iter().enumerate() {
+        let download_id = format!("batch-{}-{}", batch_started, index);
+        let _ = window.emit(
+            "batch-progress",
+            BatchProgress {
+                current: index + 1,
+                total,
+                url: url.clone(),
+                download_id: download_id.clone(),
+            },
+        );
+
+        let request = DownloadRequest {
+            download_id,
+            url: url.clone(),
+            format: format.clone(),
+            quality: quality.clone(),
+            download_path: download_path.clone(),
+            ..Default::default()
+        };
+
+        let result = download_media(
+            window.clone(),
+            downloads.clone(),
+            window.state::<HistoryStore>(),
+            window.state::<SettingsStore>(),
+            request,
+        )
+        .await;
+        match result {
+            Ok(_) => summary.succeeded.push(url),
+            // Stopping everything kills only the current item, so don't start the next
+            Err(AppError::Cancelled) => {
+                println!("Batch cancelled at {}", url);
+                summary.failed.push((url, AppError::Cancelled.to_string()));
+                break;
+            }
+            // Not a failure, the item can still be resumed on its own
+            Err(AppError::Paused) => {
+                println!("Batch paused at {}", url);
+                summary.paused = Some(url);
+                break;
+            }
+            Err(e) => {
+                println!("Batch item {} failed: {}", url, e);
+                summary.failed.push((url, e.to_string()));
+                if !continue_on_error {
+                    println!("Stopping batch after the first failure");
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Saves each format under its own name so downloads don't overwrite each other.
+const ALL_FORMATS_OUTPUT_TEMPLATE: &str = "%(title)s.f%(format_id)s.%(ext)s";
+
+/// Emitted by `download_all_formats` before each format starts downloading.
+#[derive(Clone, Serialize)]
+struct FormatProgress {
+    current: usize,
+    total: usize,
+    format_id: String,
+    /// The id the format's `download-progress` events are sent under.
+    download_id: String,
+}
+
+/// Downloads every format of a video one after another, e.g. for archiving.
+/// This can fetch many gigabytes, so without `confirm` it only fails with
+/// `AppError::ConfirmationRequired` saying how much there is. Returns the
+/// succeeded and failed format ids.
+#[tauri::command]
+async fn download_all_formats(
+    window: Window,
+    downloads: State<'_, DownloadRegistry>,
+    url: String,
+    download_path: Option<String>,
+    confirm: Option<bool>,
+) -> Result<BatchSummary, AppError> {
+    let url = validate_url(&url)?;
+    let info = dump_video_json(window.app_handle(), &url, None, None, None).await?;
+
+    // Storyboards are thumbnail sheets rather than media
+    let formats: Vec<&serde_json::Value> = info["formats"]
+        .as_array()
+        .map(|formats| {
+            formats
+                .iter()
+                .filter(|format| format["ext"].as_str() != Some("mhtml"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut format_ids: Vec<String> = Vec::new();
+    for format in &formats {
+        if let Some(id) = format["format_id"].as_str() {
+            if !format_ids.iter().any(|known| known == id) {
+                format_ids.push(id.to_string());
+            }
+        }
+    }
+    if format_ids.is_empty() {
+        return Err(AppError::NoFormats(format!(
+            "No downloadable formats found for {}",
+            url
+        )));
+    }
+
+    if !confirm.unwrap_or(false) {
+        let sizes: Vec<u64> = formats
+            .iter()
+            .copied()
+            .filter_map(format_filesize)
+            .collect();
+        return Err(AppError::ConfirmationRequired {
+            format_count: format_ids.len(),
+            // A lower bound, since some formats may not report a size
+            total_bytes: (!sizes.is_empty()).then(|| sizes.iter().sum()),
+        });
+    }
+
+    println!("Downloading all {} formats of {}", format_ids.len(), url);
+    let base_id = new_id("formats");
+    let total = format_ids.len();
+    let mut summary = BatchSummary::default();
+    for (index, format_id) in format_ids.into_iter().enumerate() {
+        let download_id = format!("{}-{}", base_id, format_id);
+        let _ = window.emit(
+            "format-progress",
+            FormatProgress {
+                current: index + 1,
+                total,
+                format_id: format_id.clone(),
+                download_id: download_id.clone(),
+            },
+        );
+
+        // "video" with a format id passes it to -f as-is, audio formats included
+        let request = DownloadRequest {
+            download_id,
+            url: url.clone(),
+            format: "video".to_string(),
+            quality: format_id.clone(),
+            download_path: download_path.clone(),
+            output_template: Some(ALL_FORMATS_OUTPUT_TEMPLATE.to_string()),
+            ..Default::default()
+        };
+
+        let result = download_media(
+            window.clone(),
+            downloads.clone(),
+            window.state::<HistoryStore>(),
+            window.state::<SettingsStore>(),
+            request,
+        )
+        .await;
+        match result {
+            Ok(_) => summary.succeeded.push(format_id),
+            // Stop rather than moving on to the next format
+            Err(AppError::Cancelled) => {
+                summary
+                    .failed
+                    .push((format_id, AppError::Cancelled.to_string()));
+                break;
+            }
+            Err(AppError::Paused) => {
+                summary.paused = Some(format_id);
+                break;
+            }
+            Err(e) => {
+                println!("Format {} of {} failed: {}", format_id, url, e);
+                summary.failed.push((format_id, e.to_string()));
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Kills every yt-dlp process still running so none outlive the app and keep
+/// their `.part` files locked.
+async fn kill_all_children(app: &AppHandle) {
+    let downloads = app.state::<DownloadRegistry>();
+    let infos = app.state::<InfoRegistry>();
+    let children: Vec<(String, Child)> = downloads
+        .children
+        .lock()
+        .await
+        .drain()
+        .chain(infos.children.lock().await.drain())
+        .collect();
+    println!("Killing {} yt-dlp processes on exit", children.len());
+
+    for (id, mut child) in children {
+        if let Err(e) = child.kill().await {
+            println!("Failed to kill yt-dlp for {}: {}", id, e);
+        }
     }
 }
 
@@ -339,11 +4414,97 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .manage(DownloadRegistry::default())
+        .manage(InfoRegistry::default())
+        .manage(ExtractorCache::default())
+        .manage(HistoryStore::default())
+        .manage(SettingsStore::default())
         .invoke_handler(tauri::generate_handler![
-            greet,
+            get_app_info,
             download_media,
-            get_video_info
+            download_batch,
+            download_all_formats,
+            build_download_command,
+            open_download_folder,
+            open_app_data_dir,
+            open_app_config_dir,
+            cancel_download,
+            cancel_all_downloads,
+            set_max_concurrent_downloads,
+            pause_download,
+            resume_download,
+            cleanup_temp_files,
+            check_ffmpeg,
+            get_ytdlp_version,
+            update_ytdlp,
+            history::get_download_history,
+            history::clear_download_history,
+            settings::get_settings,
+            settings::save_settings,
+            list_subtitles,
+            list_extractors,
+            list_formats_text,
+            estimate_size,
+            get_output_filename,
+            validate_cookies_file,
+            probe_url,
+            test_proxy,
+            get_thumbnail,
+            download_to_buffer,
+            get_raw_formats,
+            get_chapters,
+            get_playlist_info,
+            get_video_info,
+            cancel_video_info
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                tauri::async_runtime::block_on(kill_all_children(app));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_subtitle_list() {
+        let stdout = "\
+[youtube] Extracting URL: https://www.youtube.com/watch?v=dQw4w9WgXcQ
+[youtube] dQw4w9WgXcQ: Downloading webpage
+[info] Available automatic captions for dQw4w9WgXcQ:
+Language Name                  Formats
+ab       Abkhazian             vtt, ttml, srv3, srv2, srv1, json3
+[info] Available subtitles for dQw4w9WgXcQ:
+Language Name                    Formats
+en       English                 vtt, ttml, srv3, srv2, srv1, json3
+en-US    English (United States) vtt, ttml, srv3, srv2, srv1, json3
+";
+        let tracks = parse_subtitle_list(stdout).unwrap();
+
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].language, "ab");
+        assert!(tracks[0].automatic);
+        assert_eq!(tracks[2].language, "en-US");
+        assert_eq!(tracks[2].name.as_deref(), Some("English (United States)"));
+        assert_eq!(
+            tracks[2].formats,
+            ["vtt", "ttml", "srv3", "srv2", "srv1", "json3"]
+        );
+        assert!(!tracks[2].automatic);
+    }
+
+    #[test]
+    fn parses_subtitle_list_without_subtitles() {
+        let stdout = "\
+[youtube] Extracting URL: https://www.youtube.com/watch?v=jNQXAC9IVRw
+[youtube] jNQXAC9IVRw: Downloading webpage
+jNQXAC9IVRw has no automatic captions
+jNQXAC9IVRw has no subtitles
+";
+        assert!(parse_subtitle_list(stdout).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,175 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Mutex;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Formats accepted as `default_format`, as understood by `download_media`.
+pub const FORMATS: &[&str] = &[
+    "video+audio",
+    "video_audio",
+    "video",
+    "video_only",
+    "audio",
+    "audio_only",
+];
+
+/// Qualities accepted as `default_quality`. Format ids only make sense for
+/// a single video, so they can't be saved as a default.
+pub const QUALITIES: &[&str] = &["best", "worst"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Directory of the most recent download, used when none is given.
+    pub last_download_path: Option<String>,
+    /// Proxy used by info and download commands that don't set their own.
+    pub proxy: Option<String>,
+    /// Format used when a download doesn't specify one, one of `FORMATS`.
+    pub default_format: Option<String>,
+    /// Quality used when a download doesn't specify one, one of `QUALITIES`.
+    pub default_quality: Option<String>,
+    /// yt-dlp binary to use instead of the bundled one, e.g. a nightly build.
+    pub ytdlp_path: Option<String>,
+}
+
+/// Whether `path` is a file that can be run.
+pub fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+impl Settings {
+    /// Rejects a default format or quality `download_media` wouldn't accept.
+    fn validate_defaults(&self) -> Result<(), AppError> {
+        if let Some(format) = &self.default_format {
+            if !FORMATS.contains(&format.as_str()) {
+                return Err(AppError::InvalidInput(format!(
+                    "Unknown default format: {} (expected one of {})",
+                    format,
+                    FORMATS.join(", ")
+                )));
+            }
+        }
+        if let Some(quality) = &self.default_quality {
+            if !QUALITIES.contains(&quality.as_str()) {
+                return Err(AppError::InvalidInput(format!(
+                    "Unknown default quality: {} (expected one of {})",
+                    quality,
+                    QUALITIES.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects settings that can't be saved.
+    fn validate(&self) -> Result<(), AppError> {
+        self.validate_defaults()?;
+        // Checked again when used, in case the file goes away later
+        if let Some(path) = &self.ytdlp_path {
+            if !is_executable(Path::new(path)) {
+                return Err(AppError::InvalidInput(format!(
+                    "yt-dlp path isn't an executable file: {}",
+                    path
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serializes access to the settings file.
+#[derive(Default)]
+pub struct SettingsStore {
+    lock: Mutex<()>,
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, AppError> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| AppError::Io(format!("Failed to resolve app config directory: {}", e)))?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+fn read_settings(path: &Path) -> Result<Settings, AppError> {
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| AppError::Io(format!("Failed to read settings: {}", e)))?;
+    let mut settings: Settings =
+        serde_json::from_str(&contents).map_err(|e| AppError::JsonParse(e.to_string()))?;
+
+    // A hand-edited file shouldn't break every download, so drop bad defaults
+    if let Err(e) = settings.validate_defaults() {
+        println!("Ignoring saved default format and quality: {}", e);
+        settings.default_format = None;
+        settings.default_quality = None;
+    }
+    Ok(settings)
+}
+
+fn write_settings(path: &Path, settings: &Settings) -> Result<(), AppError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| AppError::Io(format!("Failed to create app config directory: {}", e)))?;
+    }
+
+    let contents =
+        serde_json::to_string_pretty(settings).map_err(|e| AppError::JsonParse(e.to_string()))?;
+    std::fs::write(path, contents)
+        .map_err(|e| AppError::Io(format!("Failed to write settings: {}", e)))
+}
+
+/// Reads the saved settings, falling back to defaults if there are none.
+pub async fn load(app: &AppHandle, store: &SettingsStore) -> Result<Settings, AppError> {
+    let _guard = store.lock.lock().await;
+    read_settings(&settings_path(app)?)
+}
+
+/// Saves `path` as the last-used download directory.
+pub async fn remember_download_path(
+    app: &AppHandle,
+    store: &SettingsStore,
+    path: &str,
+) -> Result<(), AppError> {
+    let _guard = store.lock.lock().await;
+    let settings_path = settings_path(app)?;
+    let mut settings = read_settings(&settings_path)?;
+    settings.last_download_path = Some(path.to_string());
+    write_settings(&settings_path, &settings)
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+) -> Result<Settings, AppError> {
+    load(&app, &store).await
+}
+
+#[tauri::command]
+pub async fn save_settings(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    settings: Settings,
+) -> Result<(), AppError> {
+    settings.validate()?;
+    let _guard = store.lock.lock().await;
+    write_settings(&settings_path(&app)?, &settings)
+}
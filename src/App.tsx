@@ -23,10 +23,24 @@ interface QualityOption {
   id: string;
   label: string;
   format_type: string;
+  filesize_bytes: number | null;
+  fps: number | null;
+  dynamic_range: string | null;
+  abr: number | null;
+  requires_merge: boolean;
+}
+
+interface VideoInfo {
+  title: string | null;
+  duration_seconds: number | null;
+  thumbnail_url: string | null;
+  uploader: string | null;
+  view_count: number | null;
+  qualities: QualityOption[];
 }
 
 interface LogMessage {
-  type: "stdout" | "stderr";
+  type: "stdout" | "stderr" | "warning" | "error";
   message: string;
 }
 
@@ -35,6 +49,28 @@ interface BackendLogMessage {
   message: string;
 }
 
+interface Settings {
+  last_download_path: string | null;
+  proxy: string | null;
+  default_format: string | null;
+  default_quality: string | null;
+  ytdlp_path: string | null;
+}
+
+interface AppError {
+  kind: string;
+  details?: unknown;
+}
+
+function describeError(error: unknown): string {
+  if (error && typeof error === "object" && "kind" in error) {
+    const { kind, details } = error as AppError;
+    if (details === undefined) return kind;
+    return `${kind}: ${typeof details === "string" ? details : JSON.stringify(details)}`;
+  }
+  return String(error);
+}
+
 function App() {
   const [url, setUrl] = useState("");
   const [format, setFormat] = useState("video+audio");
@@ -47,9 +83,15 @@ function App() {
   const [logs, setLogs] = useState<LogMessage[]>([]);
   const [isLogViewerOpen, setIsLogViewerOpen] = useState(false);
 
+  useEffect(() => {
+    invoke<Settings>("get_settings")
+      .then((settings) => setDownloadPath(settings.last_download_path))
+      .catch((error) => console.error(error));
+  }, []);
+
   useEffect(() => {
     const unlisten = listen<BackendLogMessage>("download-log", (event) => {
-      setLogs((prev) => [...prev, { type: event.payload.message_type as "stdout" | "stderr", message: event.payload.message }]);
+      setLogs((prev) => [...prev, { type: event.payload.message_type as LogMessage["type"], message: event.payload.message }]);
     });
 
     return () => {
@@ -65,12 +107,12 @@ function App() {
 
     setIsFetchingInfo(true);
     try {
-      const qualities = await invoke<QualityOption[]>("get_video_info", { url });
-      setAvailableQualities(qualities);
+      const info = await invoke<VideoInfo>("get_video_info", { url });
+      setAvailableQualities(info.qualities);
       toast.success("Video info fetched successfully!");
     } catch (error) {
       console.error(error);
-      toast.error(`Failed to fetch video info: ${error}`);
+      toast.error(`Failed to fetch video info: ${describeError(error)}`);
     } finally {
       setIsFetchingInfo(false);
     }
@@ -86,26 +128,33 @@ function App() {
     setProgress(0);
     setLogs([]); // Clear logs on new download
 
+    const downloadId = crypto.randomUUID();
+
     // Listen for progress events
-    const unlisten = await listen<{ progress: number; status: string }>(
+    const unlisten = await listen<{ download_id: string; progress: number; status: string }>(
       "download-progress",
       (event) => {
-        setProgress(event.payload.progress);
+        if (event.payload.download_id === downloadId) {
+          setProgress(event.payload.progress);
+        }
       }
     );
 
     try {
       await invoke("download_media", {
-        url,
-        format,
-        quality,
-        downloadPath: downloadPath,
+        request: {
+          downloadId,
+          url,
+          format,
+          quality,
+          downloadPath: downloadPath,
+        },
       });
       toast.success("Download started successfully!");
       setProgress(100);
     } catch (error) {
       console.error(error);
-      toast.error(`Failed to start download: ${error}`);
+      toast.error(`Failed to start download: ${describeError(error)}`);
     } finally {
       setIsDownloading(false);
       unlisten();
@@ -3,7 +3,7 @@ import { ScrollArea } from "./ui/scroll-area";
 import { Dialog, DialogContent, DialogHeader, DialogTitle } from "./ui/dialog";
 
 interface LogMessage {
-    type: "stdout" | "stderr";
+    type: "stdout" | "stderr" | "warning" | "error";
     message: string;
 }
 
@@ -34,11 +34,15 @@ export function LogViewer({ isOpen, onOpenChange, logs }: LogViewerProps) {
                             {logs.map((log, index) => (
                                 <div
                                     key={index}
-                                    className={`${log.type === "stderr" ? "text-red-400" : "text-zinc-300"
+                                    className={`${log.type === "warning"
+                                        ? "text-yellow-400"
+                                        : log.type === "stdout"
+                                            ? "text-zinc-300"
+                                            : "text-red-400"
                                         } break-all whitespace-pre-wrap`}
                                 >
                                     <span className="opacity-50 mr-2 select-none">
-                                        {log.type === "stderr" ? "ERR" : "OUT"}
+                                        {log.type === "warning" ? "WRN" : log.type === "stdout" ? "OUT" : "ERR"}
                                     </span>
                                     {log.message}
                                 </div>